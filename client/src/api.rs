@@ -0,0 +1,271 @@
+use crate::config::{use_api_base, ApiBase};
+use common::{paths, ErrorBody, Task, TaskPage, TaskPatch};
+use gloo_net::http::{Method, RequestBuilder};
+use gloo_timers::callback::Timeout;
+use gloo_timers::future::sleep;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+use web_sys::AbortController;
+use yew::prelude::*;
+
+/// How long a request may take, including reading the response body.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a failed `GET` is tried again.
+const RETRIES: u32 = 2;
+
+/// How long to wait before the first retry. Every further retry waits twice
+/// as long as the one before.
+const FIRST_BACKOFF: Duration = Duration::from_millis(300);
+
+/// Why a call to the server failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    /// The server could not be reached.
+    Network(String),
+    /// The server did not answer within the client's timeout.
+    Timeout,
+    /// The server answered with an error status.
+    Server { status: u16, body: ErrorBody },
+    /// The server answered with a body the client could not read.
+    Decode(String),
+}
+
+impl ApiError {
+    /// Whether the same request might succeed if it is sent again later.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ApiError::Network(_) | ApiError::Timeout => true,
+            ApiError::Server { status, .. } => matches!(status, 429 | 502 | 503 | 504),
+            ApiError::Decode(_) => false,
+        }
+    }
+
+    /// Whether the server refused the request because the task changed
+    /// since it was read, e.g. in another tab.
+    pub fn is_conflict(&self) -> bool {
+        matches!(
+            self,
+            ApiError::Server {
+                status: 409 | 412,
+                ..
+            }
+        )
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Network(err) => write!(f, "could not reach the server: {}", err),
+            ApiError::Timeout => write!(f, "the server took too long to answer"),
+            ApiError::Server { body, .. } => {
+                write!(f, "{}", body.message)?;
+                for detail in &body.details {
+                    write!(f, "; {} {}", detail.field, detail.message)?;
+                }
+                Ok(())
+            }
+            ApiError::Decode(err) => write!(f, "could not read the server's answer: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// A successful answer from the server.
+struct Answer {
+    body: String,
+}
+
+impl Answer {
+    fn json<T: DeserializeOwned>(&self) -> Result<T, ApiError> {
+        serde_json::from_str(&self.body).map_err(|err| ApiError::Decode(err.to_string()))
+    }
+}
+
+/// The body of an error response, or a stand-in for servers and proxies
+/// that do not send one.
+fn error_body(status: u16, status_text: &str, body: &str) -> ErrorBody {
+    serde_json::from_str(body).unwrap_or_else(|_| ErrorBody {
+        code: format!("http_{}", status),
+        message: if status_text.is_empty() {
+            format!("the server answered with status {}", status)
+        } else {
+            status_text.to_lowercase()
+        },
+        details: Vec::new(),
+    })
+}
+
+/// Calls the task API, in the version this client speaks.
+///
+/// Every request is cancelled after a timeout. `GET` requests that fail for
+/// reasons that may pass, like the server restarting, are retried with
+/// exponential backoff. Writes are not, since the first attempt may have
+/// reached the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiClient {
+    base: ApiBase,
+}
+
+impl ApiClient {
+    pub fn new(base: ApiBase) -> Self {
+        ApiClient { base }
+    }
+
+    /// One page of tasks, starting after the `next_cursor` of the previous page.
+    pub async fn list_tasks(&self, after: Option<&str>) -> Result<TaskPage, ApiError> {
+        let query: Vec<(&str, &str)> = after.map(|cursor| ("after", cursor)).into_iter().collect();
+        self.send(
+            Method::GET,
+            paths::TASKS,
+            |req| req.query(query.clone()),
+            None,
+        )
+        .await?
+        .json()
+    }
+
+    /// Every task, following the pages to the end.
+    pub async fn all_tasks(&self) -> Result<Vec<Task>, ApiError> {
+        let mut tasks = Vec::new();
+        let mut after = None;
+        loop {
+            let page = self.list_tasks(after.as_deref()).await?;
+            tasks.extend(page.items);
+            match page.next_cursor {
+                Some(cursor) => after = Some(cursor),
+                None => return Ok(tasks),
+            }
+        }
+    }
+
+    /// Create a task, returning it with the id and timestamps the server set.
+    pub async fn create_task(&self, task: &Task) -> Result<Task, ApiError> {
+        self.send(Method::POST, paths::TASK, |req| req, Some(to_json(task)))
+            .await?
+            .json()
+    }
+
+    /// Change some fields of a task, unless it changed on the server since
+    /// it was read.
+    pub async fn patch_task(&self, task: &Task, patch: &TaskPatch) -> Result<Task, ApiError> {
+        let etag = task.etag();
+        let path = paths::task(task.id);
+        self.send(
+            Method::PATCH,
+            &path,
+            |req| req.header("If-Match", &etag),
+            Some(to_json(patch)),
+        )
+        .await?
+        .json()
+    }
+
+    /// Delete a task, unless it changed on the server since it was read.
+    pub async fn delete_task(&self, task: &Task) -> Result<(), ApiError> {
+        let etag = task.etag();
+        let path = paths::task(task.id);
+        self.send(
+            Method::DELETE,
+            &path,
+            |req| req.header("If-Match", &etag),
+            None,
+        )
+        .await
+        .map(drop)
+    }
+
+    /// Send a request, retrying it if it is a `GET` that failed transiently.
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        prepare: impl Fn(RequestBuilder) -> RequestBuilder,
+        body: Option<String>,
+    ) -> Result<Answer, ApiError> {
+        let mut backoff = FIRST_BACKOFF;
+        let mut retries = if method == Method::GET { RETRIES } else { 0 };
+        loop {
+            match self
+                .send_once(method.clone(), path, &prepare, body.clone())
+                .await
+            {
+                Err(err) if retries > 0 && err.is_transient() => {
+                    sleep(backoff).await;
+                    backoff *= 2;
+                    retries -= 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send_once(
+        &self,
+        method: Method,
+        path: &str,
+        prepare: &impl Fn(RequestBuilder) -> RequestBuilder,
+        body: Option<String>,
+    ) -> Result<Answer, ApiError> {
+        let controller =
+            AbortController::new().map_err(|err| ApiError::Network(format!("{:?}", err)))?;
+        let timed_out = Rc::new(Cell::new(false));
+        // Cancelled when dropped, i.e. once the whole response has been read
+        let _timeout = {
+            let controller = controller.clone();
+            let timed_out = timed_out.clone();
+            Timeout::new(TIMEOUT.as_millis() as u32, move || {
+                timed_out.set(true);
+                controller.abort();
+            })
+        };
+        let failed = |err: gloo_net::Error| {
+            if timed_out.get() {
+                ApiError::Timeout
+            } else {
+                ApiError::Network(err.to_string())
+            }
+        };
+
+        let builder = prepare(
+            RequestBuilder::new(&self.base.url(path))
+                .method(method)
+                .abort_signal(Some(&controller.signal())),
+        );
+        let request = match body {
+            Some(body) => builder
+                .header("Content-Type", "application/json")
+                .body(body),
+            None => builder.build(),
+        }
+        .map_err(failed)?;
+
+        let response = request.send().await.map_err(failed)?;
+        let body = response.text().await.map_err(failed)?;
+        if response.ok() {
+            Ok(Answer { body })
+        } else {
+            let status = response.status();
+            Err(ApiError::Server {
+                status,
+                body: error_body(status, &response.status_text(), &body),
+            })
+        }
+    }
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value).expect("API types serialize to JSON")
+}
+
+/// A client for the server the surrounding [`ApiBase`] provider points at.
+#[hook]
+pub fn use_api() -> ApiClient {
+    ApiClient::new(use_api_base())
+}
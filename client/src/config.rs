@@ -0,0 +1,86 @@
+use common::paths;
+use gloo_net::http::Request;
+use serde::Deserialize;
+use std::rc::Rc;
+use web_sys::UrlSearchParams;
+use yew::prelude::*;
+
+/// Query parameter that points the client at another server, e.g.
+/// `?api=http://127.0.0.1:8000`.
+const QUERY_PARAM: &str = "api";
+
+/// `<meta>` tag in `index.html` naming the server, e.g.
+/// `<meta name="api-base-url" content="https://api.example.com" />`.
+const META_NAME: &str = "api-base-url";
+
+/// File fetched next to `index.html` on boot, so a deployment can name its
+/// server without rebuilding the client.
+const CONFIG_FILE: &str = "/config.json";
+
+/// The contents of `config.json`.
+#[derive(Deserialize)]
+struct ConfigFile {
+    api_base_url: Option<String>,
+}
+
+/// The origin of the server the client talks to, e.g.
+/// `https://api.example.com`, or empty for the origin the client was
+/// loaded from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiBase(Rc<str>);
+
+impl ApiBase {
+    fn new(url: &str) -> Self {
+        ApiBase(url.trim().trim_end_matches('/').into())
+    }
+
+    /// Work out the server to use, preferring the `api` query parameter,
+    /// then `config.json`, then the `<meta>` tag and finally the origin the
+    /// client was loaded from.
+    pub async fn resolve() -> Self {
+        let url = match from_query() {
+            Some(url) => Some(url),
+            None => from_config_file().await,
+        };
+        ApiBase::new(&url.or_else(from_meta).unwrap_or_default())
+    }
+
+    /// The URL of an API path, in the version of the API this client speaks.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}{}", self.0, paths::API_V1, path)
+    }
+}
+
+fn from_query() -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    UrlSearchParams::new_with_str(&search)
+        .ok()?
+        .get(QUERY_PARAM)
+        .filter(|url| !url.trim().is_empty())
+}
+
+/// The server named in `config.json`. A missing or unreadable file is the
+/// same as one that names no server.
+async fn from_config_file() -> Option<String> {
+    let response = Request::get(CONFIG_FILE).send().await.ok()?;
+    if !response.ok() {
+        return None;
+    }
+    let config: ConfigFile = response.json().await.ok()?;
+    config.api_base_url.filter(|url| !url.trim().is_empty())
+}
+
+fn from_meta() -> Option<String> {
+    web_sys::window()?
+        .document()?
+        .query_selector(&format!("meta[name=\"{}\"]", META_NAME))
+        .ok()??
+        .get_attribute("content")
+        .filter(|url| !url.trim().is_empty())
+}
+
+/// The server the surrounding [`ApiBase`] context provider points at.
+#[hook]
+pub fn use_api_base() -> ApiBase {
+    use_context::<ApiBase>().expect("components are rendered inside the ApiBase provider")
+}
@@ -1,22 +1,80 @@
-use gloo_net::http::Request;
-use serde::{Deserialize, Serialize};
-use serde_json;
+mod api;
+mod config;
+mod toast;
+
+use api::{use_api, ApiClient, ApiError};
+use chrono::{DateTime, Utc};
+use common::{Task, TaskPatch};
+use config::ApiBase;
+use std::rc::Rc;
+use toast::{use_toaster, ToastProvider};
 use wasm_bindgen_futures::spawn_local;
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
-const BASE_URL: &str = "http://127.0.0.1:8000";
+/// The items shown, in the order the server lists them.
+#[derive(Default, PartialEq)]
+struct Items {
+    tasks: Vec<Task>,
+}
+
+enum ItemsAction {
+    /// The server listed these items.
+    Loaded(Vec<Task>),
+    /// The server created this item.
+    Added(Task),
+    /// The item with this id changed, or a change to it is undone.
+    Updated(Task),
+    /// The item with this id is being deleted.
+    Removed(u64),
+    /// Deleting this item failed, so it is shown again.
+    Restored(Task),
+}
+
+impl Reducible for Items {
+    type Action = ItemsAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut tasks = self.tasks.clone();
+        match action {
+            ItemsAction::Loaded(loaded) => tasks = loaded,
+            ItemsAction::Added(task) | ItemsAction::Restored(task) => {
+                if !tasks.iter().any(|shown| shown.id == task.id) {
+                    // Ids grow over time, so this puts it back where it was
+                    let at = tasks.partition_point(|shown| shown.id < task.id);
+                    tasks.insert(at, task);
+                }
+            }
+            ItemsAction::Updated(task) => {
+                if let Some(shown) = tasks.iter_mut().find(|shown| shown.id == task.id) {
+                    *shown = task;
+                }
+            }
+            ItemsAction::Removed(id) => tasks.retain(|shown| shown.id != id),
+        }
+        Rc::new(Items { tasks })
+    }
+}
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-struct Item {
-    id: u8,
-    completed: bool,
-    description: String,
-    editing: bool,
+/// Show the items as they are on the server if `error` says that the one
+/// being changed was changed elsewhere first, since undoing the change here
+/// leaves an outdated copy.
+async fn reload_on_conflict(
+    error: &ApiError,
+    api: &ApiClient,
+    items: &UseReducerDispatcher<Items>,
+) {
+    if error.is_conflict() {
+        if let Ok(tasks) = api.all_tasks().await {
+            items.dispatch(ItemsAction::Loaded(tasks));
+        }
+    }
 }
 
 #[function_component(CrudItems)]
 fn crud_items() -> Html {
+    let api = use_api();
+    let toaster = use_toaster();
     let input_description_ref = use_node_ref();
     let input_description_handle = use_state(String::default);
     let input_description = (*input_description_handle).clone();
@@ -38,7 +96,7 @@ fn crud_items() -> Html {
     // State to hold the new item's name
     let input_completed_ref = use_node_ref();
     let input_completed_handle = use_state(|| false);
-    let input_completed = (*input_completed_handle).clone();
+    let input_completed = *input_completed_handle;
 
     let on_toggle = {
         let input_completed_ref = input_completed_ref.clone();
@@ -55,152 +113,131 @@ fn crud_items() -> Html {
         })
     };
 
-    let items = use_state(|| vec![]);
-    let items_handle = items.clone();
+    let items = use_reducer(Items::default);
 
-    let updated_item = use_state(|| Item {
-        id: 0,
-        completed: false,
-        description: "".to_string(),
-        editing: false,
-    });
+    // Loads every item, replacing whatever is shown
+    let load_items = {
+        let items = items.dispatcher();
+        let api = api.clone();
+        move || {
+            let items = items.clone();
+            let api = api.clone();
+            async move {
+                items.dispatch(ItemsAction::Loaded(api.all_tasks().await?));
+                Ok(())
+            }
+        }
+    };
 
     let on_fetch_items = {
-        let items = items.clone();
-        Callback::from(move |_| {
-            let items = items.clone();
-            spawn_local(async move {
-                let fetched_items: Vec<Item> = Request::get(&format!("{}/tasks", BASE_URL))
-                    .send()
-                    .await
-                    .unwrap()
-                    .json()
-                    .await
-                    .unwrap();
-                items.set(fetched_items);
-            });
-        })
+        let load_items = load_items.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |_| toaster.spawn("Could not load the items", load_items.clone()))
     };
 
     let on_submit = {
+        let items = items.dispatcher();
+        let api = api.clone();
+        let toaster = toaster.clone();
         Callback::from(move |event: SubmitEvent| {
             event.prevent_default();
-            let input_description = input_description.clone();
+            let item = Task::new(input_description.clone(), input_completed);
             let input_description_handle = input_description_handle.clone();
-            let input_completed = input_completed.clone();
-            spawn_local(async move {
-                let item = Item {
-                    id: 0,
-                    completed: input_completed,
-                    description: input_description,
-                    editing: false,
-                };
-                let json_string = serde_json::to_string(&item)
-                    .expect("Error while serializing JsValue to a string");
-
-                match Request::post(&format!("{}/task", BASE_URL))
-                    .header("Content-Type", "application/json")
-                    .body(json_string)
-                    .expect("Error while serializing the request body!")
-                    .send()
-                    .await
-                {
-                    Ok(response) => {
-                        if response.status() == 200 {
-                            input_description_handle.set(String::new());
-                        } else {
-                        }
-                    }
-                    Err(error) => {
-                        println!("Network request error: {:?}", error);
-                    }
+            let items = items.clone();
+            let api = api.clone();
+            let reporter = toaster.clone();
+            toaster.spawn("Could not add the item", move || {
+                let item = item.clone();
+                let input_description_handle = input_description_handle.clone();
+                let items = items.clone();
+                let api = api.clone();
+                let reporter = reporter.clone();
+                async move {
+                    // The server assigns the id, so the item is only shown once it answers
+                    let created = api.create_task(&item).await?;
+                    items.dispatch(ItemsAction::Added(created));
+                    input_description_handle.set(String::new());
+                    reporter.success("Item added");
+                    Ok(())
                 }
             });
         })
     };
 
-    let on_update_item = Callback::from(move |id: u64| {
-        // Use the 'id' parameter to identify the item being updated
-        let item_id = id;
-        let updated_item = updated_item.clone();
-        spawn_local(async move {
-            let item: Item = Request::get(&format!("{}/task/{}", BASE_URL, item_id.clone()))
-                .send()
-                .await
-                .unwrap()
-                .json()
-                .await
-                .unwrap();
-            updated_item.set(Item {
-                id: item.id,
-                completed: !item.completed,
-                description: item.description,
-                editing: item.editing,
-            });
-            let json_string = serde_json::to_string(&*updated_item)
-                .expect("Error while serializing JsValue to a string");
+    let on_update_item = {
+        let items = items.dispatcher();
+        let api = api.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |item: Task| {
+            let items = items.clone();
+            let api = api.clone();
+            toaster.spawn("Could not update the item", move || {
+                let item = item.clone();
+                let items = items.clone();
+                let api = api.clone();
+                async move {
+                    // Update only the item's completed status. The client sends
+                    // If-Match, so the server refuses it if another tab changed the item.
+                    let patch = TaskPatch {
+                        completed: Some(!item.completed),
+                        ..TaskPatch::default()
+                    };
+                    let mut toggled = item.clone();
+                    patch.apply(&mut toggled);
+                    items.dispatch(ItemsAction::Updated(toggled));
 
-            // Send a PUT request to update the item's completed status
-            match Request::put(&format!("{}/task/{}", BASE_URL, item_id))
-                .header("Content-Type", "application/json")
-                .body(json_string)
-                .expect("Error while serializing the request body!")
-                .send()
-                .await
-            {
-                Ok(response) => {
-                    if response.status() == 200 {
-                    } else {
+                    match api.patch_task(&item, &patch).await {
+                        Ok(updated) => {
+                            items.dispatch(ItemsAction::Updated(updated));
+                            Ok(())
+                        }
+                        Err(error) => {
+                            items.dispatch(ItemsAction::Updated(item));
+                            reload_on_conflict(&error, &api, &items).await;
+                            Err(error)
+                        }
                     }
                 }
-                Err(error) => {
-                    // Handle the error here
-                    println!("Network request error: {:?}", error);
-                }
-            }
-        });
-    });
+            });
+        })
+    };
 
-    let on_delete_item = Callback::from(move |id: u64| {
-        // Use the 'id' parameter to identify the item being deleted
-        let item_id = id;
-        spawn_local(async move {
-            // Send a Delete request to update the item's completed status
-            match Request::delete(&format!("{}/task/{}", BASE_URL, item_id))
-                .header("Content-Type", "application/json")
-                .send()
-                .await
-            {
-                Ok(response) => {
-                    if response.status() == 200 {
-                    } else {
+    let on_delete_item = {
+        let items = items.dispatcher();
+        let api = api.clone();
+        let toaster = toaster.clone();
+        Callback::from(move |item: Task| {
+            let items = items.clone();
+            let api = api.clone();
+            let reporter = toaster.clone();
+            toaster.spawn("Could not delete the item", move || {
+                let item = item.clone();
+                let items = items.clone();
+                let api = api.clone();
+                let reporter = reporter.clone();
+                async move {
+                    // Delete the version of the item we have seen
+                    items.dispatch(ItemsAction::Removed(item.id));
+                    match api.delete_task(&item).await {
+                        Ok(()) => {
+                            reporter.success("Item deleted");
+                            Ok(())
+                        }
+                        Err(error) => {
+                            items.dispatch(ItemsAction::Restored(item));
+                            reload_on_conflict(&error, &api, &items).await;
+                            Err(error)
+                        }
                     }
                 }
-                Err(error) => {
-                    // Handle the error here
-                    println!("Network request error: {:?}", error);
-                }
-            }
-        });
-    });
-
-    use_effect_with(items.clone(), move |_| {
-        // Fetch items on page refresh
-        let items_handle = items_handle.clone();
-        spawn_local(async move {
-            let fetched_items: Vec<Item> = Request::get(&format!("{}/tasks", BASE_URL))
-                .send()
-                .await
-                .unwrap()
-                .json()
-                .await
-                .unwrap();
-            items_handle.set(fetched_items);
-        });
+            });
+        })
+    };
 
-        Box::new(move || {
-            // Cleanup logic
-        }) as Box<dyn FnOnce()>
+    // Fetch items once on page load. Changes made here update the list as
+    // they happen, so only changes made elsewhere need the refresh button.
+    use_effect_with((), move |_| {
+        toaster.spawn("Could not load the items", load_items)
     });
 
     html! {
@@ -210,7 +247,7 @@ fn crud_items() -> Html {
                     <h2>{"Items Created"}</h2>
                     <button onclick={on_fetch_items}>{"Refresh Items"}</button>
                     <ul>
-                        { for items.iter().enumerate().map(|(_, item)| render_item(item.id.try_into().unwrap(), item, on_update_item.clone(), on_delete_item.clone())) }
+                        { for items.tasks.iter().map(|item| render_item(item.id, item, on_update_item.clone(), on_delete_item.clone())) }
                     </ul>
                 </div>
                 <form class="form-container" onsubmit={on_submit}>
@@ -246,12 +283,34 @@ fn crud_items() -> Html {
     }
 }
 
+/// Describe how long ago `time` was, e.g. "2 days ago".
+fn time_ago(time: DateTime<Utc>) -> String {
+    let elapsed = Utc::now().signed_duration_since(time);
+    let (count, unit) = if elapsed.num_days() > 0 {
+        (elapsed.num_days(), "day")
+    } else if elapsed.num_hours() > 0 {
+        (elapsed.num_hours(), "hour")
+    } else if elapsed.num_minutes() > 0 {
+        (elapsed.num_minutes(), "minute")
+    } else {
+        return String::from("just now");
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
 fn render_item(
     index: u64,
-    item: &Item,
-    on_update_item: Callback<u64>,
-    on_delete_item: Callback<u64>,
+    item: &Task,
+    on_update_item: Callback<Task>,
+    on_delete_item: Callback<Task>,
 ) -> Html {
+    let updated_item = item.clone();
+    let deleted_item = item.clone();
     html! {
         <li class={if item.completed { "completed" } else { "" }}>
             <span>
@@ -259,20 +318,48 @@ fn render_item(
                 {&item.description}
                 {if item.completed { " (Completed)" } else { " (Not Completed)" }}
             </span>
+            <small class="item-times">
+                {item.created_at.map(|time| format!("added {}", time_ago(time))).unwrap_or_default()}
+                {item.completed_at.map(|time| format!(", done {}", time_ago(time))).unwrap_or_default()}
+                {item.updated_at
+                    .filter(|time| Some(*time) != item.created_at)
+                    .map(|time| format!(", edited {}", time_ago(time)))
+                    .unwrap_or_default()}
+            </small>
             <input
                 type="checkbox"
                 id="item-completed"
                 name="item-completed"
                 checked={item.completed}
                 onclick={Callback::from(move |event: MouseEvent| {
-            event.prevent_default(); on_update_item.emit(index)})}
+            event.prevent_default(); on_update_item.emit(updated_item.clone())})}
             />
             <button onclick={Callback::from(move |event: MouseEvent| {
-            event.prevent_default(); on_delete_item.emit(index)})}>{"Delete"}</button>
+            event.prevent_default(); on_delete_item.emit(deleted_item.clone())})}>{"Delete"}</button>
         </li>
     }
 }
 
+#[derive(Properties, PartialEq)]
+struct AppProps {
+    api: ApiBase,
+}
+
+#[function_component(App)]
+fn app(props: &AppProps) -> Html {
+    html! {
+        <ContextProvider<ApiBase> context={props.api.clone()}>
+            <ToastProvider>
+                <CrudItems />
+            </ToastProvider>
+        </ContextProvider<ApiBase>>
+    }
+}
+
 fn main() {
-    yew::Renderer::<CrudItems>::new().render();
+    // The server has to be known before anything talks to it
+    spawn_local(async {
+        let api = ApiBase::resolve().await;
+        yew::Renderer::<App>::with_props(AppProps { api }).render();
+    });
 }
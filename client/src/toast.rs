@@ -0,0 +1,252 @@
+use crate::api::ApiError;
+use gloo_timers::callback::Timeout;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+/// Older toasts are dropped once this many are shown.
+const MAX_TOASTS: usize = 5;
+
+/// How long a toast without a retry button stays up, in milliseconds.
+const SUCCESS_MS: u32 = 3_000;
+const WARNING_MS: u32 = 6_000;
+const ERROR_MS: u32 = 8_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToastKind {
+    Success,
+    Warning,
+    Error,
+}
+
+impl ToastKind {
+    fn class(self) -> &'static str {
+        match self {
+            ToastKind::Success => "toast toast-success",
+            ToastKind::Warning => "toast toast-warning",
+            ToastKind::Error => "toast toast-error",
+        }
+    }
+}
+
+/// A message shown on top of the page.
+#[derive(Clone, PartialEq)]
+struct Toast {
+    id: usize,
+    kind: ToastKind,
+    message: String,
+    /// Runs the action that failed again. Toasts with one stay up until they
+    /// are dismissed, so the button does not disappear under the cursor.
+    retry: Option<Callback<()>>,
+}
+
+impl Toast {
+    /// How long the toast stays up, or `None` if it waits to be dismissed.
+    fn lifetime_ms(&self) -> Option<u32> {
+        if self.retry.is_some() {
+            return None;
+        }
+        Some(match self.kind {
+            ToastKind::Success => SUCCESS_MS,
+            ToastKind::Warning => WARNING_MS,
+            ToastKind::Error => ERROR_MS,
+        })
+    }
+}
+
+/// The toasts on screen, oldest first.
+#[derive(Default, PartialEq)]
+struct Toasts {
+    next_id: usize,
+    shown: Vec<Toast>,
+}
+
+enum ToastAction {
+    Show {
+        kind: ToastKind,
+        message: String,
+        retry: Option<Callback<()>>,
+    },
+    Dismiss(usize),
+}
+
+impl Reducible for Toasts {
+    type Action = ToastAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut shown = self.shown.clone();
+        let mut next_id = self.next_id;
+        match action {
+            ToastAction::Show {
+                kind,
+                message,
+                retry,
+            } => {
+                shown.push(Toast {
+                    id: next_id,
+                    kind,
+                    message,
+                    retry,
+                });
+                next_id += 1;
+                let excess = shown.len().saturating_sub(MAX_TOASTS);
+                shown.drain(..excess);
+            }
+            ToastAction::Dismiss(id) => shown.retain(|toast| toast.id != id),
+        }
+        Rc::new(Toasts { next_id, shown })
+    }
+}
+
+/// A call to the server that can be made again.
+type Call = Rc<dyn Fn() -> Pin<Box<dyn Future<Output = Result<(), ApiError>>>>>;
+
+/// Shows toasts from any component below a [`ToastProvider`].
+#[derive(Clone, PartialEq)]
+pub struct Toaster {
+    dispatch: UseReducerDispatcher<Toasts>,
+}
+
+impl Toaster {
+    pub fn show(&self, kind: ToastKind, message: impl Into<String>) {
+        self.dispatch.dispatch(ToastAction::Show {
+            kind,
+            message: message.into(),
+            retry: None,
+        });
+    }
+
+    pub fn success(&self, message: impl Into<String>) {
+        self.show(ToastKind::Success, message);
+    }
+
+    pub fn warning(&self, message: impl Into<String>) {
+        self.show(ToastKind::Warning, message);
+    }
+
+    pub fn error(&self, message: impl Into<String>) {
+        self.show(ToastKind::Error, message);
+    }
+
+    /// Show an error with a button that runs `retry`.
+    pub fn error_with_retry(&self, message: impl Into<String>, retry: Callback<()>) {
+        self.dispatch.dispatch(ToastAction::Show {
+            kind: ToastKind::Error,
+            message: message.into(),
+            retry: Some(retry),
+        });
+    }
+
+    /// Make a call to the server in the background, reporting a failure as
+    /// `failure` followed by the reason.
+    ///
+    /// Failures that may pass offer to make the call again, and conflicts
+    /// with changes made elsewhere are shown as warnings.
+    pub fn spawn<F, Fut>(&self, failure: &'static str, call: F)
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = Result<(), ApiError>> + 'static,
+    {
+        self.spawn_call(failure, Rc::new(move || Box::pin(call())));
+    }
+
+    fn spawn_call(&self, failure: &'static str, call: Call) {
+        let toaster = self.clone();
+        spawn_local(async move {
+            let Err(error) = call().await else {
+                return;
+            };
+            let message = format!("{}: {}", failure, error);
+            if error.is_transient() {
+                let retry = {
+                    let toaster = toaster.clone();
+                    Callback::from(move |()| toaster.spawn_call(failure, call.clone()))
+                };
+                toaster.error_with_retry(message, retry);
+            } else if error.is_conflict() {
+                toaster.warning(message);
+            } else {
+                toaster.error(message);
+            }
+        });
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ToastProviderProps {
+    pub children: Html,
+}
+
+/// Provides a [`Toaster`] to its children and shows their toasts stacked in
+/// a corner of the page.
+#[function_component(ToastProvider)]
+pub fn toast_provider(props: &ToastProviderProps) -> Html {
+    let toasts = use_reducer(Toasts::default);
+    let toaster = Toaster {
+        dispatch: toasts.dispatcher(),
+    };
+    let on_dismiss = {
+        let dispatch = toasts.dispatcher();
+        Callback::from(move |id| dispatch.dispatch(ToastAction::Dismiss(id)))
+    };
+
+    html! {
+        <ContextProvider<Toaster> context={toaster}>
+            { props.children.clone() }
+            <div class="toasts" role="status" aria-live="polite">
+                { for toasts.shown.iter().map(|toast| html! {
+                    <ToastView key={toast.id} toast={toast.clone()} on_dismiss={on_dismiss.clone()} />
+                }) }
+            </div>
+        </ContextProvider<Toaster>>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct ToastViewProps {
+    toast: Toast,
+    on_dismiss: Callback<usize>,
+}
+
+#[function_component(ToastView)]
+fn toast_view(props: &ToastViewProps) -> Html {
+    let toast = &props.toast;
+    let id = toast.id;
+
+    {
+        let on_dismiss = props.on_dismiss.clone();
+        let lifetime_ms = toast.lifetime_ms();
+        use_effect_with(id, move |_| {
+            let timeout = lifetime_ms.map(|ms| Timeout::new(ms, move || on_dismiss.emit(id)));
+            // Dropping the timeout cancels it if the toast was dismissed first
+            move || drop(timeout)
+        });
+    }
+
+    let on_close = props.on_dismiss.reform(move |_: MouseEvent| id);
+    let on_retry = toast.retry.clone().map(|retry| {
+        let on_dismiss = props.on_dismiss.clone();
+        Callback::from(move |_: MouseEvent| {
+            on_dismiss.emit(id);
+            retry.emit(());
+        })
+    });
+
+    html! {
+        <div class={toast.kind.class()}>
+            <span class="toast-message">{ &toast.message }</span>
+            if let Some(on_retry) = on_retry {
+                <button class="toast-retry" onclick={on_retry}>{ "Retry" }</button>
+            }
+            <button class="toast-close" aria-label="Dismiss" onclick={on_close}>{ "×" }</button>
+        </div>
+    }
+}
+
+/// The toaster of the surrounding [`ToastProvider`].
+#[hook]
+pub fn use_toaster() -> Toaster {
+    use_context::<Toaster>().expect("components are rendered inside the ToastProvider")
+}
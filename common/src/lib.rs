@@ -0,0 +1,9 @@
+//! Types exchanged between the server and the client.
+//!
+//! Both sides depend on this crate, so changing the API shape here is a
+//! compile error wherever it is used.
+
+mod models;
+pub mod paths;
+
+pub use crate::models::{ErrorBody, ErrorDetail, Task, TaskPage, TaskPatch, MAX_DESCRIPTION_LEN};
@@ -0,0 +1,162 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Longest description accepted, in characters.
+pub const MAX_DESCRIPTION_LEN: usize = 1000;
+
+/// Check a task description, returning what is wrong with it if anything.
+fn check_description(description: &str) -> Option<String> {
+    if description.trim().is_empty() {
+        Some(String::from("must not be empty"))
+    } else if description.chars().count() > MAX_DESCRIPTION_LEN {
+        Some(format!(
+            "must be at most {} characters long",
+            MAX_DESCRIPTION_LEN
+        ))
+    } else {
+        None
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Task {
+    pub id: u64,
+    pub completed: bool,
+    #[cfg_attr(feature = "openapi", schema(max_length = 1000))]
+    pub description: String,
+    pub editing: bool,
+    /// Revision counter managed by the server. It starts at 1 and is bumped
+    /// on every write; values sent by clients are ignored.
+    #[serde(default)]
+    pub version: u64,
+    /// When the task was created. Set by the server; `None` for tasks stored
+    /// before timestamps were recorded.
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// When the task was last written. Set by the server.
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// When the task was last marked as completed, or `None` while it is open.
+    /// Set by the server.
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl Task {
+    /// A task to send to the server for creation. The server assigns the id,
+    /// version and timestamps.
+    pub fn new(description: impl Into<String>, completed: bool) -> Self {
+        Task {
+            id: 0,
+            completed,
+            description: description.into(),
+            editing: false,
+            version: 0,
+            created_at: None,
+            updated_at: None,
+            completed_at: None,
+        }
+    }
+
+    /// Check the fields a client can set, returning one detail per problem.
+    pub fn validate(&self) -> Vec<ErrorDetail> {
+        check_description(&self.description)
+            .map(|message| ErrorDetail::new("description", message))
+            .into_iter()
+            .collect()
+    }
+
+    /// The entity tag for this revision of the task, as sent in the `ETag` header.
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+}
+
+/// One page of tasks returned by `GET /tasks`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TaskPage {
+    pub items: Vec<Task>,
+    /// Pass as `after` to fetch the next page. `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// A partial update of a task. Fields that are left out keep their current value.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskPatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "openapi", schema(max_length = 1000))]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editing: Option<bool>,
+}
+
+impl TaskPatch {
+    /// Check the fields present in this patch, returning one detail per problem.
+    pub fn validate(&self) -> Vec<ErrorDetail> {
+        self.description
+            .as_deref()
+            .and_then(check_description)
+            .map(|message| ErrorDetail::new("description", message))
+            .into_iter()
+            .collect()
+    }
+
+    /// Apply the fields present in this patch to `task`.
+    pub fn apply(&self, task: &mut Task) {
+        if let Some(completed) = self.completed {
+            task.completed = completed;
+        }
+        if let Some(description) = &self.description {
+            task.description = description.clone();
+        }
+        if let Some(editing) = self.editing {
+            task.editing = editing;
+        }
+    }
+}
+
+/// The JSON body returned with every error response.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorBody {
+    /// A short machine-readable error code such as `not_found`.
+    pub code: String,
+    /// A human-readable description of what went wrong.
+    pub message: String,
+    /// Problems with individual fields of the request body, if any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<ErrorDetail>,
+}
+
+/// A problem with one field of a submitted task.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorDetail {
+    /// Position of the task in the request body, for requests carrying a list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    pub field: String,
+    pub message: String,
+}
+
+impl ErrorDetail {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        ErrorDetail {
+            index: None,
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Attach the position of the task this detail is about.
+    pub fn at(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+}
@@ -0,0 +1,15 @@
+//! Paths of the API routes, relative to where the server mounts them.
+
+/// Where version 1 of the API is mounted. Prepend it to the paths below.
+pub const API_V1: &str = "/api/v1";
+
+/// Create a task with `POST`. Single tasks live below it.
+pub const TASK: &str = "/task";
+
+/// List tasks with `GET` or replace all of them with `POST`.
+pub const TASKS: &str = "/tasks";
+
+/// The path of the task with this id.
+pub fn task(id: u64) -> String {
+    format!("{}/{}", TASK, id)
+}
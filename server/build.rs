@@ -0,0 +1,28 @@
+use std::process::Command;
+
+/// Expose the commit being built as `GIT_SHA` for the `/version` route.
+///
+/// A `GIT_SHA` set in the environment wins, for builds without a `.git`
+/// directory such as container images.
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs");
+
+    let sha = std::env::var("GIT_SHA").ok().or_else(|| {
+        let output = Command::new("git")
+            .args(["rev-parse", "--short=12", "HEAD"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8(output.stdout)
+            .ok()
+            .map(|sha| sha.trim().to_string())
+    });
+
+    if let Some(sha) = sha.filter(|sha| !sha.is_empty()) {
+        println!("cargo:rustc-env=GIT_SHA={}", sha);
+    }
+}
@@ -0,0 +1,21 @@
+//! Runs the server on its own, without Shuttle.
+//!
+//! Listens on the `address` and `port` from the configuration.
+
+use server::{build_rocket, init_logging, AppConfig};
+
+#[rocket::launch]
+fn rocket() -> _ {
+    let config = AppConfig::load().and_then(|config| {
+        init_logging(&config.logging)?;
+        Ok(config)
+    });
+    match config.and_then(build_rocket) {
+        Ok(rocket) => rocket,
+        Err(err) => {
+            // Logging is not set up when the configuration cannot be read
+            eprintln!("Error in configuration: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use crate::cors::config_cors;
+use crate::database::StorageBackend;
+use rocket::figment::providers::{Env, Serialized};
+use rocket::figment::{self, Figment};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use tracing_subscriber::EnvFilter;
+
+/// Errors found while loading the configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// A value is missing or has the wrong type.
+    Parse(Box<figment::Error>),
+    /// A value was read but cannot be used.
+    Invalid { key: &'static str, message: String },
+}
+
+impl ConfigError {
+    pub(crate) fn invalid(key: &'static str, message: impl Into<String>) -> Self {
+        ConfigError::Invalid {
+            key,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Parse(err) => write!(f, "could not read the configuration: {}", err),
+            ConfigError::Invalid { key, message } => {
+                write!(f, "invalid value for `{}`: {}", key, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<figment::Error> for ConfigError {
+    fn from(err: figment::Error) -> Self {
+        ConfigError::Parse(Box::new(err))
+    }
+}
+
+/// Tuning for the sled storage backend.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct SledConfig {
+    /// Size of the page cache in bytes.
+    pub cache_capacity: u64,
+    /// How often sled flushes to disk in the background, in milliseconds.
+    /// `0` disables background flushes.
+    pub flush_every_ms: u64,
+    /// Flush every write to disk before responding, so that a crash cannot
+    /// lose acknowledged writes. Slower, but does not depend on shutting down
+    /// cleanly.
+    pub durable: bool,
+}
+
+impl Default for SledConfig {
+    fn default() -> Self {
+        SledConfig {
+            cache_capacity: 1_000_000,
+            flush_every_ms: 1000,
+            durable: false,
+        }
+    }
+}
+
+/// Which cross-origin requests browsers may make.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Exact origins such as `https://example.com`, or just `*` to allow any origin.
+    pub allowed_origins: Vec<String>,
+    /// Regular expressions matched against the origin, e.g.
+    /// `^https://.+\.example\.com$`. Unanchored patterns match anywhere in it.
+    pub allowed_origin_patterns: Vec<String>,
+    /// HTTP methods such as `GET`.
+    pub allowed_methods: Vec<String>,
+    /// Request headers, or `*` to allow any header.
+    pub allowed_headers: Vec<String>,
+    /// Response headers that browsers let scripts read.
+    pub expose_headers: Vec<String>,
+    /// Whether browsers may send cookies and credentials.
+    pub allow_credentials: bool,
+    /// How long browsers may cache preflight responses, in seconds.
+    pub max_age: Option<usize>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: vec![String::from("*")],
+            allowed_origin_patterns: Vec::new(),
+            allowed_methods: ["GET", "POST", "OPTIONS", "PUT", "PATCH", "DELETE"]
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            allowed_headers: vec![String::from("*")],
+            // Let browsers read task versions for use in `If-Match`
+            expose_headers: vec![String::from("ETag"), String::from("Location")],
+            allow_credentials: false,
+            max_age: None,
+        }
+    }
+}
+
+/// The Prometheus endpoint at `/metrics`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Whether requests are measured and `/metrics` is served.
+    pub enabled: bool,
+    /// If set, `/metrics` only answers requests carrying
+    /// `Authorization: Bearer <token>`.
+    pub token: Option<String>,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            enabled: true,
+            token: None,
+        }
+    }
+}
+
+/// How log lines are written.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines, for reading in a terminal.
+    #[default]
+    Pretty,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+/// What the server logs and how.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// Which events are logged, as a `tracing` filter such as `info` or
+    /// `info,server=debug`.
+    pub filter: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            format: LogFormat::default(),
+            filter: String::from("info"),
+        }
+    }
+}
+
+/// The compiled Yew client served next to the API.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct FrontendConfig {
+    /// Whether the client is served at all.
+    pub enabled: bool,
+    /// The Trunk `dist` directory to serve. Ignored when the server is built
+    /// with the `embed-client` feature, which serves the copy in the binary.
+    pub dir: PathBuf,
+}
+
+impl Default for FrontendConfig {
+    fn default() -> Self {
+        FrontendConfig {
+            enabled: true,
+            dir: PathBuf::from("../client/dist"),
+        }
+    }
+}
+
+/// Everything the server can be configured with.
+///
+/// Loaded by [`AppConfig::figment`] from, in increasing priority, the
+/// defaults below, `Rocket.toml`, `ROCKET_` environment variables and `APP_`
+/// environment variables. Nested keys use `__` in environment variables, e.g.
+/// `APP_SLED__CACHE_CAPACITY=10000000` or `APP_CORS__ALLOWED_ORIGINS=[...]`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AppConfig {
+    /// The storage backend tasks are kept in.
+    pub storage: StorageBackend,
+    /// Where the storage backend keeps its data.
+    pub database_path: PathBuf,
+    pub sled: SledConfig,
+    pub cors: CorsConfig,
+    pub metrics: MetricsConfig,
+    /// Not `log`, which Rocket warns about as a deprecated key of its own.
+    pub logging: LogConfig,
+    pub frontend: FrontendConfig,
+    /// The address to listen on. Shuttle picks its own when deployed there.
+    pub address: IpAddr,
+    /// The port to listen on. Shuttle picks its own when deployed there.
+    pub port: u16,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            storage: StorageBackend::default(),
+            database_path: PathBuf::from("data.db"),
+            sled: SledConfig::default(),
+            cors: CorsConfig::default(),
+            metrics: MetricsConfig::default(),
+            logging: LogConfig::default(),
+            frontend: FrontendConfig::default(),
+            address: Ipv4Addr::LOCALHOST.into(),
+            port: 8000,
+        }
+    }
+}
+
+impl AppConfig {
+    /// The layered configuration sources, also used to configure Rocket itself.
+    pub fn figment() -> Figment {
+        rocket::Config::figment()
+            .join(Serialized::defaults(AppConfig::default()))
+            .merge(Env::prefixed("APP_").split("__").global())
+    }
+
+    /// Read the configuration from the default sources and validate it.
+    pub fn load() -> Result<AppConfig, ConfigError> {
+        AppConfig::from_figment(&AppConfig::figment())
+    }
+
+    /// Read the configuration from `figment` and validate it.
+    pub fn from_figment(figment: &Figment) -> Result<AppConfig, ConfigError> {
+        let config: AppConfig = figment.extract()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the values that deserialize fine but cannot be used.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.storage != StorageBackend::Memory && self.database_path.as_os_str().is_empty() {
+            return Err(ConfigError::invalid("database_path", "must not be empty"));
+        }
+        if self.sled.cache_capacity == 0 {
+            return Err(ConfigError::invalid(
+                "sled.cache_capacity",
+                "must be greater than 0",
+            ));
+        }
+
+        if self.metrics.token.as_ref().is_some_and(String::is_empty) {
+            return Err(ConfigError::invalid(
+                "metrics.token",
+                "must not be empty, leave it out to serve metrics without a token",
+            ));
+        }
+
+        if let Err(err) = EnvFilter::try_new(&self.logging.filter) {
+            return Err(ConfigError::invalid(
+                "logging.filter",
+                format!("`{}` is not a valid filter: {}", self.logging.filter, err),
+            ));
+        }
+
+        config_cors(&self.cors)?;
+
+        Ok(())
+    }
+}
@@ -1,28 +1,142 @@
-use rocket::http::Method;
-use rocket_cors::{AllOrSome, AllowedHeaders, Cors, CorsOptions, Origins};
-
-pub fn config_cors(allowed_origins: AllOrSome<Origins>, allowed_methods: Vec<Method>) -> Cors {
-    // Check for an empty vector of allowed methods
-    if allowed_methods.is_empty() {
-        eprintln!("Error: Empty vector of allowed methods");
-        return CorsOptions::default().to_cors().unwrap();
+use crate::config::{ConfigError, CorsConfig};
+use rocket_cors::{AllowedHeaders, AllowedOrigins, Cors, CorsOptions, Method};
+use std::str::FromStr;
+
+/// Build the CORS fairing described by the configuration.
+///
+/// Every setting is checked up front, and the error names the key and value
+/// that cannot be used, so a typo stops the server instead of loosening or
+/// tightening its CORS rules.
+pub fn config_cors(config: &CorsConfig) -> Result<Cors, ConfigError> {
+    cors_options(config)?.to_cors().map_err(|err| {
+        // Everything rocket_cors checks has been checked above already
+        ConfigError::invalid("cors", err.to_string())
+    })
+}
+
+/// Translate the CORS section of the configuration into rocket_cors options.
+pub fn cors_options(config: &CorsConfig) -> Result<CorsOptions, ConfigError> {
+    let allowed_origins = allowed_origins(config)?;
+
+    if config.allowed_methods.is_empty() {
+        return Err(ConfigError::invalid(
+            "cors.allowed_methods",
+            "must list at least one method",
+        ));
+    }
+    let allowed_methods = config
+        .allowed_methods
+        .iter()
+        .map(|method| {
+            Method::from_str(&method.to_uppercase()).map_err(|_| {
+                ConfigError::invalid(
+                    "cors.allowed_methods",
+                    format!("`{}` is not an HTTP method", method),
+                )
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    let allowed_headers = if config.allowed_headers == ["*"] {
+        AllowedHeaders::all()
+    } else {
+        check_header_names("cors.allowed_headers", &config.allowed_headers)?;
+        let headers: Vec<&str> = config.allowed_headers.iter().map(String::as_str).collect();
+        AllowedHeaders::some(&headers)
+    };
+
+    check_header_names("cors.expose_headers", &config.expose_headers)?;
+
+    Ok(CorsOptions {
+        allowed_origins,
+        allowed_methods,
+        allowed_headers,
+        allow_credentials: config.allow_credentials,
+        expose_headers: config.expose_headers.iter().cloned().collect(),
+        max_age: config.max_age,
+        ..CorsOptions::default()
+    })
+}
+
+/// Check the exact and pattern origins and combine them into an allow-list.
+fn allowed_origins(config: &CorsConfig) -> Result<AllowedOrigins, ConfigError> {
+    let exact = &config.allowed_origins;
+    let patterns = &config.allowed_origin_patterns;
+
+    if exact.iter().any(|origin| origin == "*") {
+        if exact.len() > 1 || !patterns.is_empty() {
+            return Err(ConfigError::invalid(
+                "cors.allowed_origins",
+                "\"*\" allows any origin and cannot be combined with other origins or patterns",
+            ));
+        }
+        if config.allow_credentials {
+            return Err(ConfigError::invalid(
+                "cors.allow_credentials",
+                "cannot be enabled while any origin (\"*\") is allowed",
+            ));
+        }
+        return Ok(AllowedOrigins::all());
+    }
+
+    if exact.is_empty() && patterns.is_empty() {
+        return Err(ConfigError::invalid(
+            "cors.allowed_origins",
+            "must list at least one origin or pattern, or \"*\"",
+        ));
+    }
+
+    // Parse each entry on its own so the error can name the one that is wrong
+    for origin in exact {
+        parse_origins(AllowedOrigins::some_exact(&[origin])).map_err(|err| {
+            ConfigError::invalid(
+                "cors.allowed_origins",
+                format!("`{}` is not a valid origin: {}", origin, err),
+            )
+        })?;
     }
+    for pattern in patterns {
+        parse_origins(AllowedOrigins::some_regex(&[pattern])).map_err(|err| {
+            ConfigError::invalid(
+                "cors.allowed_origin_patterns",
+                format!("`{}` is not a valid pattern: {}", pattern, err),
+            )
+        })?;
+    }
+
+    Ok(AllowedOrigins::some(exact, patterns))
+}
 
-    // Attempt to create CORS configuration
-    let cors = CorsOptions {
-        allowed_origins: allowed_origins,
-        allowed_methods: allowed_methods.into_iter().map(From::from).collect(),
-        allowed_headers: AllowedHeaders::all(),
+/// Let rocket_cors parse an origin allow-list, describing what it rejected.
+fn parse_origins(allowed_origins: AllowedOrigins) -> Result<(), String> {
+    let options = CorsOptions {
+        allowed_origins,
         ..CorsOptions::default()
+    };
+    match options.to_cors() {
+        Ok(_) => Ok(()),
+        Err(rocket_cors::Error::BadOrigin(err)) => Err(err.to_string()),
+        Err(rocket_cors::Error::OpaqueAllowedOrigin(_)) => Err(String::from(
+            "it has no scheme and host, use a pattern instead",
+        )),
+        Err(err) => Err(err.to_string()),
     }
-    .to_cors();
-
-    // Check for an error in CORS setup
-    match cors {
-        Ok(cors) => cors,
-        Err(err) => {
-            eprintln!("Error in CORS setup: {}", err);
-            CorsOptions::default().to_cors().unwrap()
+}
+
+/// Check that every entry is a plausible HTTP header name.
+fn check_header_names(key: &'static str, headers: &[String]) -> Result<(), ConfigError> {
+    for header in headers {
+        let valid = !header.is_empty()
+            && header
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(ConfigError::invalid(
+                key,
+                format!("`{}` is not a valid header name", header),
+            ));
         }
     }
+
+    Ok(())
 }
@@ -0,0 +1,159 @@
+use super::{
+    check_version, stamp_created, stamp_updated, StorageBackend, StoreError, StoreResult,
+    StoreStats, TaskStore,
+};
+use crate::query::{SortField, SortOrder, TaskQuery};
+use common::{Task, TaskPage, TaskPatch};
+use std::collections::BTreeMap;
+use std::ops::Bound::{Excluded, Unbounded};
+use std::sync::{Mutex, MutexGuard};
+
+/// A task store that keeps everything in memory.
+///
+/// Nothing is persisted, which makes it a good fit for tests and ephemeral
+/// demos.
+#[derive(Default)]
+pub struct MemoryStore {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    next_id: u64,
+    tasks: BTreeMap<u64, Task>,
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        // A panic while holding the lock cannot leave the map half-written,
+        // so it is safe to keep using it.
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl TaskStore for MemoryStore {
+    fn create(&self, mut task: Task) -> StoreResult<Task> {
+        let mut inner = self.lock();
+
+        task.id = inner.next_id;
+        task.version = 1;
+        stamp_created(&mut task);
+        inner.next_id += 1;
+        inner.tasks.insert(task.id, task.clone());
+
+        Ok(task)
+    }
+
+    fn get(&self, id: u64) -> StoreResult<Task> {
+        self.lock()
+            .tasks
+            .get(&id)
+            .cloned()
+            .ok_or(StoreError::NotFound(id))
+    }
+
+    fn list(&self, query: &TaskQuery) -> StoreResult<TaskPage> {
+        let inner = self.lock();
+        if query.sort != SortField::Id {
+            return query.page_unsorted(inner.tasks.values().cloned().collect());
+        }
+
+        // Walk the map from the cursor in id order
+        let after = query.cursor()?.map(|cursor| cursor.id());
+        let tasks: Box<dyn Iterator<Item = &Task>> = match query.order {
+            SortOrder::Asc => Box::new(
+                inner
+                    .tasks
+                    .range((after.map_or(Unbounded, Excluded), Unbounded))
+                    .map(|(_, task)| task),
+            ),
+            SortOrder::Desc => Box::new(
+                inner
+                    .tasks
+                    .range((Unbounded, after.map_or(Unbounded, Excluded)))
+                    .rev()
+                    .map(|(_, task)| task),
+            ),
+        };
+
+        query.page_sorted(tasks.cloned().map(Ok))
+    }
+
+    fn update(&self, id: u64, mut task: Task, expected: Option<&[u64]>) -> StoreResult<Task> {
+        match self.lock().tasks.get_mut(&id) {
+            Some(stored) => {
+                check_version(stored, expected)?;
+                task.id = id;
+                task.version = stored.version + 1;
+                stamp_updated(stored, &mut task);
+                *stored = task.clone();
+                Ok(task)
+            }
+            None => Err(StoreError::NotFound(id)),
+        }
+    }
+
+    fn patch(&self, id: u64, patch: &TaskPatch, expected: Option<&[u64]>) -> StoreResult<Task> {
+        match self.lock().tasks.get_mut(&id) {
+            Some(stored) => {
+                check_version(stored, expected)?;
+                let mut task = stored.clone();
+                patch.apply(&mut task);
+                task.version += 1;
+                stamp_updated(stored, &mut task);
+                *stored = task;
+                Ok(stored.clone())
+            }
+            None => Err(StoreError::NotFound(id)),
+        }
+    }
+
+    fn delete(&self, id: u64, expected: Option<&[u64]>) -> StoreResult<()> {
+        let mut inner = self.lock();
+        let stored = inner.tasks.get(&id).ok_or(StoreError::NotFound(id))?;
+        check_version(stored, expected)?;
+        inner.tasks.remove(&id);
+
+        Ok(())
+    }
+
+    fn replace_all(&self, tasks: Vec<Task>) -> StoreResult<()> {
+        let mut inner = self.lock();
+        inner.tasks.clear();
+
+        // Fresh ids keep stale ETags of the old tasks from matching the new ones
+        for mut task in tasks {
+            task.id = inner.next_id;
+            task.version = 1;
+            stamp_created(&mut task);
+            inner.next_id += 1;
+            inner.tasks.insert(task.id, task);
+        }
+
+        Ok(())
+    }
+
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Memory
+    }
+
+    fn check(&self) -> StoreResult<()> {
+        // Taking the lock is the only thing that can go wrong
+        drop(self.lock());
+        Ok(())
+    }
+
+    fn stats(&self) -> StoreResult<StoreStats> {
+        let inner = self.lock();
+        Ok(StoreStats {
+            tasks: inner.tasks.len() as u64,
+            completed: inner.tasks.values().filter(|task| task.completed).count() as u64,
+            size_on_disk: None,
+        })
+    }
+}
@@ -0,0 +1,260 @@
+use crate::config::AppConfig;
+use crate::query::TaskQuery;
+use chrono::Utc;
+use common::{Task, TaskPage, TaskPatch};
+use rocket::fairing::AdHoc;
+use rocket::futures::future::{self, BoxFuture};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+mod memory_store;
+mod sled_store;
+#[cfg(feature = "sqlite")]
+mod sqlite_store;
+
+pub use memory_store::MemoryStore;
+pub use sled_store::{setup_database, SledStore};
+#[cfg(feature = "sqlite")]
+pub use sqlite_store::{setup_sqlite_database, SqliteStore};
+
+/// Errors returned by a [`TaskStore`].
+#[derive(Debug)]
+pub enum StoreError {
+    /// There is no task with this id.
+    NotFound(u64),
+    /// The request is not valid for the store.
+    Invalid(String),
+    /// The write conflicts with the current state of the store.
+    Conflict(String),
+    /// The task is not at any of the versions the caller expected.
+    VersionMismatch {
+        id: u64,
+        expected: Vec<u64>,
+        actual: u64,
+    },
+    /// The backend failed to read or write.
+    Backend(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::NotFound(id) => write!(f, "task with id {} does not exist", id),
+            StoreError::Invalid(message) => write!(f, "invalid request: {}", message),
+            StoreError::Conflict(message) => write!(f, "conflict: {}", message),
+            StoreError::VersionMismatch {
+                id,
+                expected,
+                actual,
+            } => {
+                write!(f, "task with id {} is at version {}, not ", id, actual)?;
+                match expected.as_slice() {
+                    [] => write!(f, "any version given"),
+                    [expected] => write!(f, "{}", expected),
+                    expected => {
+                        let versions: Vec<String> = expected.iter().map(u64::to_string).collect();
+                        write!(f, "{}", versions.join(" or "))
+                    }
+                }
+            }
+            StoreError::Backend(message) => write!(f, "storage error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<sled::Error> for StoreError {
+    fn from(err: sled::Error) -> Self {
+        StoreError::Backend(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(err: serde_json::Error) -> Self {
+        StoreError::Backend(format!("could not encode task: {}", err))
+    }
+}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+/// Errors opening a store on startup.
+#[derive(Debug)]
+pub enum OpenError {
+    /// Another process has the database open.
+    Locked(PathBuf),
+    /// The database files are damaged.
+    Corrupted { path: PathBuf, message: String },
+    /// The database could not be opened or migrated for another reason.
+    Backend { path: PathBuf, message: String },
+}
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenError::Locked(path) => write!(
+                f,
+                "the database at {} is in use by another process; stop the other \
+                 server or point `database_path` somewhere else",
+                path.display()
+            ),
+            OpenError::Corrupted { path, message } => write!(
+                f,
+                "the database at {} is corrupted ({}); restore it from a backup or \
+                 move it away to start with an empty one",
+                path.display(),
+                message
+            ),
+            OpenError::Backend { path, message } => {
+                write!(
+                    f,
+                    "could not open the database at {}: {}",
+                    path.display(),
+                    message
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for OpenError {}
+
+/// Check that a task is at one of the `expected` versions, if they are given.
+pub(crate) fn check_version(task: &Task, expected: Option<&[u64]>) -> StoreResult<()> {
+    match expected {
+        Some(expected) if !expected.contains(&task.version) => Err(StoreError::VersionMismatch {
+            id: task.id,
+            expected: expected.to_vec(),
+            actual: task.version,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Stamp a task that is about to be stored for the first time.
+///
+/// Timestamps sent by the client are discarded.
+pub(crate) fn stamp_created(task: &mut Task) {
+    let now = Utc::now();
+    task.created_at = Some(now);
+    task.updated_at = Some(now);
+    task.completed_at = task.completed.then_some(now);
+}
+
+/// Stamp a task that is about to overwrite `stored`.
+///
+/// The creation time is carried over from `stored`, and the completion time
+/// only changes when the task is completed or reopened.
+pub(crate) fn stamp_updated(stored: &Task, task: &mut Task) {
+    let now = Utc::now();
+    task.created_at = stored.created_at;
+    task.updated_at = Some(now);
+    task.completed_at = match (stored.completed, task.completed) {
+        (_, false) => None,
+        (true, true) => stored.completed_at,
+        (false, true) => Some(now),
+    };
+}
+
+/// Figures about the stored tasks, reported as metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StoreStats {
+    /// How many tasks are stored.
+    pub tasks: u64,
+    /// How many of them are completed.
+    pub completed: u64,
+    /// How many bytes the store takes up on disk, for stores kept on disk.
+    pub size_on_disk: Option<u64>,
+}
+
+/// Storage for tasks.
+///
+/// Routes only talk to the store through this trait, so the backend can be
+/// swapped without touching them. Implementations are shared between request
+/// handlers and therefore must be `Send + Sync`.
+///
+/// Stores own the `id`, `version` and timestamps of every task. Writes to an
+/// existing task take optional `expected` versions and fail with
+/// [`StoreError::VersionMismatch`] instead of overwriting a newer revision.
+pub trait TaskStore: Send + Sync {
+    /// Store a new task at version 1 and return it. The id will be assigned by the store.
+    fn create(&self, task: Task) -> StoreResult<Task>;
+
+    /// Get a task by id.
+    fn get(&self, id: u64) -> StoreResult<Task>;
+
+    /// Return one page of the tasks matching `query`.
+    fn list(&self, query: &TaskQuery) -> StoreResult<TaskPage>;
+
+    /// Overwrite an existing task and return it.
+    fn update(&self, id: u64, task: Task, expected: Option<&[u64]>) -> StoreResult<Task>;
+
+    /// Atomically apply a partial update to an existing task and return the result.
+    fn patch(&self, id: u64, patch: &TaskPatch, expected: Option<&[u64]>) -> StoreResult<Task>;
+
+    /// Delete an existing task.
+    fn delete(&self, id: u64, expected: Option<&[u64]>) -> StoreResult<()>;
+
+    /// Replace all tasks with the given ones.
+    fn replace_all(&self, tasks: Vec<Task>) -> StoreResult<()>;
+
+    /// The backend this store keeps tasks in.
+    fn backend(&self) -> StorageBackend;
+
+    /// Check that the store can currently be read from and written to.
+    fn check(&self) -> StoreResult<()>;
+
+    /// Count the stored tasks and measure the store.
+    fn stats(&self) -> StoreResult<StoreStats>;
+
+    /// Write buffered changes to disk and return how many bytes were written.
+    ///
+    /// Stores that write through on every change have nothing to flush.
+    fn flush(&self) -> BoxFuture<'static, StoreResult<usize>> {
+        Box::pin(future::ready(Ok(0)))
+    }
+}
+
+/// The storage backends the server can run on.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// Tasks are kept in a sled database.
+    #[default]
+    Sled,
+    /// Tasks are kept in a SQLite database. Requires the `sqlite` feature.
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    /// Tasks are kept in memory and lost on shutdown.
+    Memory,
+}
+
+/// Open the task store selected by `config`, running any pending migrations.
+pub fn open_store(config: &AppConfig) -> Result<Arc<dyn TaskStore>, OpenError> {
+    let path = config.database_path.clone();
+    Ok(match config.storage {
+        StorageBackend::Sled => Arc::new(setup_database(path, &config.sled)?),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite => Arc::new(setup_sqlite_database(path)?),
+        StorageBackend::Memory => Arc::new(MemoryStore::new()),
+    })
+}
+
+/// A fairing that opens the store selected by `config` on ignite and manages
+/// it as `Arc<dyn TaskStore>`.
+///
+/// If the store cannot be opened the error is logged and ignition fails, so
+/// the server never starts without its data.
+pub fn store_fairing(config: AppConfig) -> AdHoc {
+    AdHoc::try_on_ignite("Task Store", |rocket| async move {
+        match open_store(&config) {
+            Ok(store) => Ok(rocket.manage(store)),
+            Err(err) => {
+                tracing::error!(error = %err, "could not open the task store");
+                Err(rocket)
+            }
+        }
+    })
+}
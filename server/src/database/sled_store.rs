@@ -0,0 +1,385 @@
+use super::{
+    check_version, stamp_created, stamp_updated, OpenError, StorageBackend, StoreError,
+    StoreResult, StoreStats, TaskStore,
+};
+use crate::config::SledConfig;
+use crate::query::{SortField, SortOrder, TaskQuery};
+use chrono::Utc;
+use common::{Task, TaskPage, TaskPatch};
+use rocket::futures::future::BoxFuture;
+use serde_json::{from_slice, to_vec};
+use sled::Mode::LowSpace;
+use sled::{Batch, Config, Db, IVec, Tree};
+use std::ops::Bound::{Excluded, Unbounded};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// The key readiness checks write to in the health tree.
+const HEALTH_PROBE_KEY: &[u8] = b"probe";
+
+/// How many generated ids `create` tries before giving up.
+///
+/// Generated ids only collide with keys carried over from older databases or
+/// written by `replace_all`, so running out means something is badly wrong.
+const MAX_ID_ATTEMPTS: usize = 1024;
+
+/// A task store backed by a sled database.
+///
+/// The `Db` handle is kept around so new ids can be drawn from
+/// `Db::generate_id`, which is monotonic and survives restarts.
+///
+/// Writes are buffered by sled and flushed in the background unless the store
+/// is `durable`, in which case every write is flushed before it returns.
+#[derive(Clone)]
+pub struct SledStore {
+    db: Db,
+    tree: Tree,
+    /// Holds the key written by readiness checks, away from the tasks.
+    health: Tree,
+    durable: bool,
+    /// Held for reading by `create` and for writing by `replace_all`, so a
+    /// task created while the old keys are being collected cannot outlive
+    /// the replacement. Other writes only touch keys `replace_all` removes.
+    replacing: Arc<RwLock<()>>,
+}
+
+/// Encode a task id as a big-endian key so that sled iterates tasks in id order.
+fn task_key(id: u64) -> [u8; 8] {
+    id.to_be_bytes()
+}
+
+/// Decode a task id from a big-endian key.
+fn task_id(key: &[u8]) -> Option<u64> {
+    key.try_into().ok().map(u64::from_be_bytes)
+}
+
+/// Decode a stored task, taking the id from its key.
+fn decode_task(id: u64, value: &IVec) -> StoreResult<Task> {
+    let mut task: Task = from_slice(value)?;
+    task.id = id;
+    Ok(task)
+}
+
+/// Decode an entry read while iterating over the tree.
+///
+/// Entries that cannot be decoded are logged and skipped rather than failing
+/// the whole list.
+fn decode_entry(entry: sled::Result<(IVec, IVec)>) -> Option<StoreResult<Task>> {
+    let (k, v) = match entry {
+        Ok(entry) => entry,
+        Err(err) => return Some(Err(err.into())),
+    };
+    let id = task_id(&k)?;
+    match decode_task(id, &v) {
+        // Successfully deserialized the JSON into a Task
+        Ok(decoded) => Some(Ok(decoded)),
+        Err(err) => {
+            tracing::warn!(task_id = id, error = %err, "skipping a task that cannot be decoded");
+            None
+        }
+    }
+}
+
+impl SledStore {
+    /// Flush a write to disk before reporting it done, if the store is durable.
+    fn written<T>(&self, result: T) -> StoreResult<T> {
+        if self.durable {
+            self.db.flush()?;
+        }
+        Ok(result)
+    }
+
+    /// Replace an existing task with the result of `f`, bump its version and
+    /// update its timestamps.
+    ///
+    /// The write is a compare-and-swap against the value `f` was given, so
+    /// concurrent writers never overwrite each other's changes. If the task
+    /// changed in the meantime `f` is called again with the new value, unless
+    /// the caller `expected` a specific version, which is then gone.
+    fn modify<F>(&self, id: u64, expected: Option<&[u64]>, f: F) -> StoreResult<Task>
+    where
+        F: Fn(Task) -> StoreResult<Task>,
+    {
+        loop {
+            let current = self
+                .tree
+                .get(task_key(id))?
+                .ok_or(StoreError::NotFound(id))?;
+            let stored = decode_task(id, &current)?;
+            check_version(&stored, expected)?;
+
+            let version = stored.version + 1;
+            let mut task = f(stored.clone())?;
+            task.id = id;
+            task.version = version;
+            stamp_updated(&stored, &mut task);
+            let encoded = to_vec(&task)?;
+
+            let swapped = self
+                .tree
+                .compare_and_swap(task_key(id), Some(current), Some(encoded))?;
+            if swapped.is_ok() {
+                return Ok(task);
+            }
+        }
+    }
+}
+
+impl TaskStore for SledStore {
+    fn create(&self, mut task: Task) -> StoreResult<Task> {
+        // Draw ids until one is free. Keys carried over from older databases
+        // may already occupy generated ids.
+        task.version = 1;
+        stamp_created(&mut task);
+        // Nothing is guarded by the lock, so a poisoned one is still usable
+        let _replacing = self.replacing.read().unwrap_or_else(|err| err.into_inner());
+        for _ in 0..MAX_ID_ATTEMPTS {
+            task.id = self.db.generate_id()?;
+
+            // Serialize the task into a Vec<u8>
+            let encoded = to_vec(&task)?;
+
+            // Only insert if the key does not exist yet
+            let inserted = self.tree.compare_and_swap(
+                task_key(task.id),
+                None as Option<&[u8]>,
+                Some(encoded),
+            )?;
+            if inserted.is_ok() {
+                tracing::debug!(task_id = task.id, "task created");
+                return self.written(task);
+            }
+        }
+
+        Err(StoreError::Conflict(String::from(
+            "could not find a free id for the new task",
+        )))
+    }
+
+    fn list(&self, query: &TaskQuery) -> StoreResult<TaskPage> {
+        if query.sort != SortField::Id {
+            // Keys are in id order, so other orders need every task in memory
+            let tasks = self
+                .tree
+                .iter()
+                .filter_map(decode_entry)
+                .collect::<StoreResult<Vec<Task>>>()?;
+            return query.page_unsorted(tasks);
+        }
+
+        // Scan from the cursor in key order and stop once the page is full
+        let after = query.cursor()?.map(|cursor| task_key(cursor.id()));
+        let entries: Box<dyn Iterator<Item = sled::Result<(IVec, IVec)>>> = match query.order {
+            SortOrder::Asc => Box::new(
+                self.tree
+                    .range((after.map_or(Unbounded, Excluded), Unbounded)),
+            ),
+            SortOrder::Desc => Box::new(
+                self.tree
+                    .range((Unbounded, after.map_or(Unbounded, Excluded)))
+                    .rev(),
+            ),
+        };
+
+        query.page_sorted(entries.filter_map(decode_entry))
+    }
+
+    /// Get a task by id from the database.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the task to retrieve.
+    ///
+    /// # Returns
+    ///
+    /// Returns the retrieved task, `StoreError::NotFound` if there is no task
+    /// with this id, or the error that occurred while reading it.
+    fn get(&self, id: u64) -> StoreResult<Task> {
+        // Retrieve the task from the database based on the provided id
+        match self.tree.get(task_key(id))? {
+            Some(val) => decode_task(id, &val),
+            None => Err(StoreError::NotFound(id)),
+        }
+    }
+
+    fn update(&self, id: u64, task: Task, expected: Option<&[u64]>) -> StoreResult<Task> {
+        let task = self.modify(id, expected, |_| Ok(task.clone()))?;
+        tracing::debug!(task_id = id, version = task.version, "task updated");
+
+        self.written(task)
+    }
+
+    fn patch(&self, id: u64, patch: &TaskPatch, expected: Option<&[u64]>) -> StoreResult<Task> {
+        let task = self.modify(id, expected, |mut task| {
+            patch.apply(&mut task);
+            Ok(task)
+        })?;
+        tracing::debug!(task_id = id, version = task.version, "task patched");
+
+        self.written(task)
+    }
+
+    fn delete(&self, id: u64, expected: Option<&[u64]>) -> StoreResult<()> {
+        // Remove the task only if it did not change since its version was checked
+        loop {
+            let current = self
+                .tree
+                .get(task_key(id))?
+                .ok_or(StoreError::NotFound(id))?;
+            check_version(&decode_task(id, &current)?, expected)?;
+
+            let removed =
+                self.tree
+                    .compare_and_swap(task_key(id), Some(current), None as Option<IVec>)?;
+            if removed.is_ok() {
+                tracing::debug!(task_id = id, "task deleted");
+                return self.written(());
+            }
+        }
+    }
+
+    fn replace_all(&self, tasks: Vec<Task>) -> StoreResult<()> {
+        let mut batch = Batch::default();
+        let count = tasks.len();
+        let _replacing = self
+            .replacing
+            .write()
+            .unwrap_or_else(|err| err.into_inner());
+
+        // Delete every key currently in the tree, whatever its id
+        for key in self.tree.iter().keys() {
+            batch.remove(key?);
+        }
+
+        // Serialize everything before writing so a bad task cannot leave a partial write
+        // Fresh ids keep stale ETags of the old tasks from matching the new ones
+        for mut task in tasks {
+            task.id = self.db.generate_id()?;
+            task.version = 1;
+            stamp_created(&mut task);
+            batch.insert(&task_key(task.id), to_vec(&task)?);
+        }
+
+        // Apply the removals and inserts atomically
+        self.tree.apply_batch(batch)?;
+        tracing::info!(tasks = count, "replaced all tasks");
+
+        self.written(())
+    }
+
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Sled
+    }
+
+    fn check(&self) -> StoreResult<()> {
+        // Write a fresh value to the probe key and read it back
+        let probe = Utc::now().to_rfc3339();
+        self.health.insert(HEALTH_PROBE_KEY, probe.as_bytes())?;
+        match self.health.get(HEALTH_PROBE_KEY)? {
+            Some(value) if value == probe.as_bytes() => {}
+            _ => {
+                return Err(StoreError::Backend(String::from(
+                    "the health probe did not read back what was written",
+                )))
+            }
+        }
+        self.tree.first()?;
+
+        Ok(())
+    }
+
+    fn stats(&self) -> StoreResult<StoreStats> {
+        let mut stats = StoreStats {
+            size_on_disk: Some(self.db.size_on_disk()?),
+            ..StoreStats::default()
+        };
+        for task in self.tree.iter().filter_map(decode_entry) {
+            stats.tasks += 1;
+            stats.completed += u64::from(task?.completed);
+        }
+
+        Ok(stats)
+    }
+
+    fn flush(&self) -> BoxFuture<'static, StoreResult<usize>> {
+        let db = self.db.clone();
+        Box::pin(async move { Ok(db.flush_async().await?) })
+    }
+}
+
+/// Rewrite the one-byte keys used by older versions into big-endian u64 keys.
+///
+/// Databases created before ids were widened stored each task under a single
+/// byte. The rewrite happens in one batch so a crash cannot leave a task
+/// under both keys.
+fn migrate_legacy_keys(tree: &Tree) -> sled::Result<usize> {
+    let mut batch = Batch::default();
+    let mut migrated = 0;
+
+    for item in tree.iter() {
+        let (key, value) = item?;
+        if let [id] = key.as_ref() {
+            batch.remove(key.clone());
+            batch.insert(&task_key(u64::from(*id)), value);
+            migrated += 1;
+        }
+    }
+
+    if migrated > 0 {
+        tree.apply_batch(batch)?;
+    }
+
+    Ok(migrated)
+}
+
+/// Describe why sled could not open or migrate the database at `path`.
+fn open_error(path: PathBuf, err: sled::Error) -> OpenError {
+    match err {
+        // sled reports a lock held by another process as a plain I/O error
+        sled::Error::Io(ref io) if io.to_string().starts_with("could not acquire lock") => {
+            OpenError::Locked(path)
+        }
+        sled::Error::Corruption { .. } => OpenError::Corrupted {
+            path,
+            message: err.to_string(),
+        },
+        err => OpenError::Backend {
+            path,
+            message: err.to_string(),
+        },
+    }
+}
+
+/// Set up a sled database, bring it up to date and return a SledStore.
+pub fn setup_database(path: PathBuf, tuning: &SledConfig) -> Result<SledStore, OpenError> {
+    // Configure sled with the provided path and options
+    let flush_every_ms = Some(tuning.flush_every_ms).filter(|&ms| ms > 0);
+    let config = Config::new()
+        .path(&path)
+        .mode(LowSpace)
+        .cache_capacity(tuning.cache_capacity)
+        .flush_every_ms(flush_every_ms);
+
+    // Open the tree within the database
+    let db = config.open().map_err(|err| open_error(path.clone(), err))?;
+    let tree = db
+        .open_tree("tree")
+        .map_err(|err| open_error(path.clone(), err))?;
+    let health = db
+        .open_tree("health")
+        .map_err(|err| open_error(path.clone(), err))?;
+
+    // Bring keys written by older versions up to date
+    let migrated = migrate_legacy_keys(&tree).map_err(|err| open_error(path, err))?;
+    if migrated > 0 {
+        tracing::info!(migrated, "migrated tasks to u64 keys");
+    }
+
+    Ok(SledStore {
+        db,
+        tree,
+        health,
+        durable: tuning.durable,
+        replacing: Arc::default(),
+    })
+}
@@ -0,0 +1,397 @@
+use super::{
+    check_version, stamp_created, stamp_updated, OpenError, StorageBackend, StoreError,
+    StoreResult, StoreStats, TaskStore,
+};
+use crate::query::{SortField, SortOrder, TaskQuery};
+use chrono::Utc;
+use common::{Task, TaskPage, TaskPatch};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, ErrorCode, OptionalExtension, Row};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+/// A task store backed by a SQLite database.
+///
+/// Tasks are stored one column per field so the database can be queried with
+/// the regular `sqlite3` tooling.
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+/// Schema created on startup if it does not exist yet.
+///
+/// `AUTOINCREMENT` keeps ids monotonic, so a deleted id is never handed out
+/// again, which matches how ids are generated by the sled store.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS tasks (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        completed INTEGER NOT NULL,
+        description TEXT NOT NULL,
+        editing INTEGER NOT NULL,
+        version INTEGER NOT NULL DEFAULT 1,
+        created_at TEXT,
+        updated_at TEXT,
+        completed_at TEXT
+    );
+    CREATE TABLE IF NOT EXISTS health (
+        probe INTEGER PRIMARY KEY,
+        checked_at TEXT NOT NULL
+    );
+";
+
+/// Columns added after the first release of the schema, with their definitions.
+///
+/// Databases created by older versions are brought up to date on startup by
+/// adding whichever of these columns are missing.
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("version", "INTEGER NOT NULL DEFAULT 1"),
+    ("created_at", "TEXT"),
+    ("updated_at", "TEXT"),
+    ("completed_at", "TEXT"),
+];
+
+/// The columns `task_from_row` expects, in order.
+const TASK_COLUMNS: &str =
+    "id, completed, description, editing, version, created_at, updated_at, completed_at";
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(err: rusqlite::Error) -> Self {
+        match err.sqlite_error_code() {
+            Some(ErrorCode::ConstraintViolation) => StoreError::Conflict(err.to_string()),
+            _ => StoreError::Backend(err.to_string()),
+        }
+    }
+}
+
+/// Map a row selected with [`TASK_COLUMNS`].
+fn task_from_row(row: &Row) -> rusqlite::Result<Task> {
+    Ok(Task {
+        id: row.get::<_, i64>(0)? as u64,
+        completed: row.get(1)?,
+        description: row.get(2)?,
+        editing: row.get(3)?,
+        version: row.get::<_, i64>(4)? as u64,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+        completed_at: row.get(7)?,
+    })
+}
+
+/// Select a single task by id.
+fn select_task(conn: &Connection, id: u64) -> StoreResult<Task> {
+    conn.query_row(
+        &format!("SELECT {} FROM tasks WHERE id = ?1", TASK_COLUMNS),
+        params![row_id(id)?],
+        task_from_row,
+    )
+    .optional()?
+    .ok_or(StoreError::NotFound(id))
+}
+
+/// Write every field of an existing task back to its row.
+fn write_task(conn: &Connection, task: &Task) -> StoreResult<()> {
+    conn.execute(
+        "UPDATE tasks SET completed = ?2, description = ?3, editing = ?4, version = ?5,
+         created_at = ?6, updated_at = ?7, completed_at = ?8
+         WHERE id = ?1",
+        params![
+            row_id(task.id)?,
+            task.completed,
+            task.description,
+            task.editing,
+            task.version as i64,
+            task.created_at,
+            task.updated_at,
+            task.completed_at
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Convert a task id to an SQLite integer. Ids that do not fit cannot exist.
+fn row_id(id: u64) -> StoreResult<i64> {
+    i64::try_from(id).map_err(|_| StoreError::NotFound(id))
+}
+
+impl SqliteStore {
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        // rusqlite rolls back unfinished transactions on drop, so a panic
+        // while holding the lock does not leave the connection half-used.
+        self.conn.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl TaskStore for SqliteStore {
+    fn create(&self, mut task: Task) -> StoreResult<Task> {
+        let conn = self.lock();
+
+        // Insert the task and let SQLite assign the id
+        task.version = 1;
+        stamp_created(&mut task);
+        conn.execute(
+            "INSERT INTO tasks
+             (completed, description, editing, version, created_at, updated_at, completed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                task.completed,
+                task.description,
+                task.editing,
+                task.version as i64,
+                task.created_at,
+                task.updated_at,
+                task.completed_at
+            ],
+        )?;
+        task.id = conn.last_insert_rowid() as u64;
+        tracing::debug!(task_id = task.id, "task created");
+
+        Ok(task)
+    }
+
+    fn list(&self, query: &TaskQuery) -> StoreResult<TaskPage> {
+        // Build the filters, the keyset condition for the cursor and the order
+        let mut conditions = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
+
+        if let Some(completed) = query.completed {
+            values.push(Value::from(completed));
+            conditions.push(format!("completed = ?{}", values.len()));
+        }
+
+        let column = match query.sort {
+            SortField::Id => None,
+            SortField::Description => Some("description"),
+            SortField::Completed => Some("completed"),
+        };
+        let (direction, comparison) = match query.order {
+            SortOrder::Asc => ("ASC", ">"),
+            SortOrder::Desc => ("DESC", "<"),
+        };
+
+        if let Some(cursor) = query.cursor()? {
+            values.push(Value::from(row_id(cursor.id())?));
+            let id_param = values.len();
+            match (column, cursor.sort_value()) {
+                (Some(column), Some(value)) => {
+                    values.push(value);
+                    conditions.push(format!(
+                        "({}, id) {} (?{}, ?{})",
+                        column,
+                        comparison,
+                        values.len(),
+                        id_param
+                    ));
+                }
+                _ => conditions.push(format!("id {} ?{}", comparison, id_param)),
+            }
+        }
+
+        let order_by = match column {
+            Some(column) => format!("{} {}, id {}", column, direction, direction),
+            None => format!("id {}", direction),
+        };
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        // SQLite's `lower()` only folds ASCII, so `q` is matched in Rust by
+        // `page_sorted`, which reads rows lazily and stops once the page is
+        // full. Without it, fetch one row more than the page size to know
+        // whether there is a next page.
+        let limit = if query.q.is_none() {
+            values.push(Value::from((query.limit() + 1) as i64));
+            format!("LIMIT ?{}", values.len())
+        } else {
+            String::new()
+        };
+        let sql = format!(
+            "SELECT {} FROM tasks {} ORDER BY {} {}",
+            TASK_COLUMNS, filter, order_by, limit
+        );
+
+        let conn = self.lock();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(values), task_from_row)?;
+
+        // Skip rows that fail to decode, like the sled store does
+        query.page_sorted(rows.filter_map(|row| match row {
+            Ok(task) => Some(Ok(task)),
+            Err(err) => {
+                tracing::warn!(error = %err, "skipping a task that cannot be decoded");
+                None
+            }
+        }))
+    }
+
+    fn get(&self, id: u64) -> StoreResult<Task> {
+        select_task(&self.lock(), id)
+    }
+
+    fn update(&self, id: u64, mut task: Task, expected: Option<&[u64]>) -> StoreResult<Task> {
+        let mut conn = self.lock();
+
+        // Check the stored version and write inside one transaction
+        let tx = conn.transaction()?;
+        let stored = select_task(&tx, id)?;
+        check_version(&stored, expected)?;
+        task.id = id;
+        task.version = stored.version + 1;
+        stamp_updated(&stored, &mut task);
+        write_task(&tx, &task)?;
+        tx.commit()?;
+        tracing::debug!(task_id = id, version = task.version, "task updated");
+
+        Ok(task)
+    }
+
+    fn patch(&self, id: u64, patch: &TaskPatch, expected: Option<&[u64]>) -> StoreResult<Task> {
+        let mut conn = self.lock();
+
+        // Read, merge and write back inside one transaction
+        let tx = conn.transaction()?;
+        let stored = select_task(&tx, id)?;
+        check_version(&stored, expected)?;
+        let mut task = stored.clone();
+        patch.apply(&mut task);
+        task.version += 1;
+        stamp_updated(&stored, &mut task);
+        write_task(&tx, &task)?;
+        tx.commit()?;
+        tracing::debug!(task_id = id, version = task.version, "task patched");
+
+        Ok(task)
+    }
+
+    fn delete(&self, id: u64, expected: Option<&[u64]>) -> StoreResult<()> {
+        let mut conn = self.lock();
+
+        let tx = conn.transaction()?;
+        check_version(&select_task(&tx, id)?, expected)?;
+        tx.execute("DELETE FROM tasks WHERE id = ?1", params![row_id(id)?])?;
+        tx.commit()?;
+        tracing::debug!(task_id = id, "task deleted");
+
+        Ok(())
+    }
+
+    fn replace_all(&self, tasks: Vec<Task>) -> StoreResult<()> {
+        let mut conn = self.lock();
+
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM tasks", [])?;
+        let count = tasks.len();
+        // AUTOINCREMENT never reuses ids, so stale ETags of the old tasks
+        // cannot match the new ones
+        for mut task in tasks {
+            stamp_created(&mut task);
+            tx.execute(
+                "INSERT INTO tasks
+                 (completed, description, editing, version, created_at, updated_at, completed_at)
+                 VALUES (?1, ?2, ?3, 1, ?4, ?5, ?6)",
+                params![
+                    task.completed,
+                    task.description,
+                    task.editing,
+                    task.created_at,
+                    task.updated_at,
+                    task.completed_at
+                ],
+            )?;
+        }
+        tx.commit()?;
+        tracing::info!(tasks = count, "replaced all tasks");
+
+        Ok(())
+    }
+
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Sqlite
+    }
+
+    fn check(&self) -> StoreResult<()> {
+        // Write to the health table, away from the tasks, and read the tasks
+        let conn = self.lock();
+        conn.execute(
+            "INSERT OR REPLACE INTO health (probe, checked_at) VALUES (1, ?1)",
+            params![Utc::now()],
+        )?;
+        conn.query_row("SELECT COUNT(*) FROM tasks", [], |row| row.get::<_, i64>(0))?;
+
+        Ok(())
+    }
+
+    fn stats(&self) -> StoreResult<StoreStats> {
+        let conn = self.lock();
+        let (tasks, completed) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(completed), 0) FROM tasks",
+            [],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+        )?;
+        let size_on_disk = conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get::<_, i64>(0),
+        )?;
+
+        Ok(StoreStats {
+            tasks: tasks as u64,
+            completed: completed as u64,
+            size_on_disk: Some(size_on_disk as u64),
+        })
+    }
+}
+
+/// Describe why SQLite could not open or migrate the database at `path`.
+fn open_error(path: PathBuf, err: rusqlite::Error) -> OpenError {
+    match err.sqlite_error_code() {
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => OpenError::Locked(path),
+        Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) => OpenError::Corrupted {
+            path,
+            message: err.to_string(),
+        },
+        _ => OpenError::Backend {
+            path,
+            message: err.to_string(),
+        },
+    }
+}
+
+/// Set up a SQLite database, creating or migrating the schema if needed, and
+/// return a SqliteStore.
+pub fn setup_sqlite_database(path: PathBuf) -> Result<SqliteStore, OpenError> {
+    // Open the database file, creating it if it does not exist
+    let conn = Connection::open(&path).map_err(|err| open_error(path.clone(), err))?;
+
+    // Create the tasks table and add columns that databases created by older
+    // versions are missing
+    conn.execute_batch(SCHEMA)
+        .and_then(|()| migrate_schema(&conn))
+        .map_err(|err| open_error(path, err))?;
+
+    Ok(SqliteStore {
+        conn: Mutex::new(conn),
+    })
+}
+
+/// Add any of the [`ADDED_COLUMNS`] that the tasks table does not have yet.
+fn migrate_schema(conn: &Connection) -> rusqlite::Result<()> {
+    let existing = conn
+        .prepare("SELECT name FROM pragma_table_info('tasks')")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (name, definition) in ADDED_COLUMNS {
+        if !existing.iter().any(|column| column == name) {
+            conn.execute_batch(&format!(
+                "ALTER TABLE tasks ADD COLUMN {} {}",
+                name, definition
+            ))?;
+            tracing::info!(column = name, "added a column to the tasks table");
+        }
+    }
+
+    Ok(())
+}
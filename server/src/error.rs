@@ -0,0 +1,98 @@
+use crate::database::StoreError;
+use common::{ErrorBody, ErrorDetail};
+use rocket::http::Status;
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+use rocket::{Catcher, Request};
+
+/// An error returned by a route, rendered as an [`ErrorBody`] with a matching status.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: Status,
+    pub code: &'static str,
+    pub message: String,
+    pub details: Vec<ErrorDetail>,
+}
+
+impl ApiError {
+    pub fn new(status: Status, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+            details: Vec::new(),
+        }
+    }
+
+    /// Reject a request body that failed validation, listing every problem found.
+    pub fn validation(details: Vec<ErrorDetail>) -> Self {
+        ApiError {
+            details,
+            ..ApiError::new(
+                Status::BadRequest,
+                "validation_failed",
+                "the request body failed validation",
+            )
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        ApiError::new(Status::BadRequest, "bad_request", message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ApiError::new(Status::NotFound, "not_found", message)
+    }
+}
+
+impl From<StoreError> for ApiError {
+    fn from(err: StoreError) -> Self {
+        let message = err.to_string();
+        match err {
+            StoreError::NotFound(_) => ApiError::not_found(message),
+            StoreError::Invalid(_) => ApiError::bad_request(message),
+            StoreError::Conflict(_) => ApiError::new(Status::Conflict, "conflict", message),
+            StoreError::VersionMismatch { .. } => {
+                ApiError::new(Status::PreconditionFailed, "precondition_failed", message)
+            }
+            StoreError::Backend(_) => {
+                ApiError::new(Status::InternalServerError, "internal_error", message)
+            }
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        if self.status.class().is_server_error() {
+            tracing::error!(status = self.status.code, error = %self.message, "request failed");
+        }
+
+        let body = ErrorBody {
+            code: self.code.to_string(),
+            message: self.message,
+            details: self.details,
+        };
+        (self.status, Json(body)).respond_to(req)
+    }
+}
+
+/// Render errors raised outside of the routes, such as unknown paths or
+/// malformed request bodies, with the same JSON body as route errors.
+#[catch(default)]
+fn default_catcher(status: Status, _req: &Request) -> ApiError {
+    let code = match status.code {
+        400 => "bad_request",
+        401 => "unauthorized",
+        404 => "not_found",
+        412 => "precondition_failed",
+        422 => "unprocessable_entity",
+        500 => "internal_error",
+        _ => "error",
+    };
+    ApiError::new(status, code, status.reason_lossy())
+}
+
+pub fn all_catchers() -> Vec<Catcher> {
+    catchers![default_catcher]
+}
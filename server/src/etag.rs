@@ -0,0 +1,121 @@
+use crate::error::ApiError;
+use common::Task;
+use rocket::http::Header;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::Json;
+use std::convert::Infallible;
+use utoipa::openapi::path::{Parameter, ParameterBuilder, ParameterIn};
+use utoipa::openapi::schema::{ObjectBuilder, Type};
+use utoipa::openapi::Required;
+use utoipa::IntoParams;
+
+/// The versions a client expects a task to be at, taken from the `If-Match` header.
+///
+/// Tags are the `"<version>"` values sent in `ETag` headers, and the header
+/// may list several of them. A missing header or `*` matches any version.
+/// Weak tags and strong tags that are not versions never match, so they fail
+/// with 412 like a stale tag. A header that is not a list of entity tags is
+/// rejected with 400 Bad Request once the route asks for the versions.
+pub struct IfMatch(Result<Option<Vec<u64>>, String>);
+
+impl IfMatch {
+    /// The versions the task may be at, or `None` if any version will do.
+    pub fn versions(&self) -> Result<Option<&[u64]>, ApiError> {
+        match &self.0 {
+            Ok(versions) => Ok(versions.as_deref()),
+            Err(value) => Err(ApiError::bad_request(format!(
+                "invalid If-Match header: {}",
+                value
+            ))),
+        }
+    }
+}
+
+/// Parse a comma-separated list of entity tags such as `"3", W/"2"` into the
+/// versions named by its strong tags, or `None` if it is not such a list.
+fn parse_etags(value: &str) -> Option<Vec<u64>> {
+    let mut versions = Vec::new();
+    let mut tags = 0;
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        // Empty list elements are allowed
+        if let Some(after) = rest.strip_prefix(',') {
+            rest = after.trim_start();
+            continue;
+        }
+
+        let weak = rest.starts_with("W/");
+        let quoted = rest.strip_prefix("W/").unwrap_or(rest).strip_prefix('"')?;
+        let end = quoted.find('"')?;
+        let opaque = &quoted[..end];
+        if !opaque
+            .bytes()
+            .all(|b| b == b'!' || (b'#'..=b'~').contains(&b) || b >= 0x80)
+        {
+            return None;
+        }
+        if !weak && !opaque.is_empty() && opaque.bytes().all(|b| b.is_ascii_digit()) {
+            // Versions too large to parse cannot match either
+            versions.extend(opaque.parse::<u64>().ok());
+        }
+        tags += 1;
+
+        rest = quoted[end + 1..].trim_start();
+        if !rest.is_empty() && !rest.starts_with(',') {
+            return None;
+        }
+    }
+
+    (tags > 0).then_some(versions)
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfMatch {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        // Repeated headers are one list
+        let values: Vec<&str> = req.headers().get("If-Match").collect();
+        if values.is_empty() {
+            return Outcome::Success(IfMatch(Ok(None)));
+        }
+
+        let value = values.join(", ");
+        if value.trim() == "*" {
+            return Outcome::Success(IfMatch(Ok(None)));
+        }
+        Outcome::Success(IfMatch(parse_etags(&value).map(Some).ok_or(value)))
+    }
+}
+
+impl IntoParams for IfMatch {
+    fn into_params(_parameter_in: impl Fn() -> Option<ParameterIn>) -> Vec<Parameter> {
+        vec![ParameterBuilder::new()
+            .name("If-Match")
+            .parameter_in(ParameterIn::Header)
+            .required(Required::False)
+            .description(Some(
+                "The `ETag` of the task as last read, or a comma-separated list of \
+                 them. The request fails with 412 if the task is at none of them. \
+                 Leave out or send `*` to skip the check.",
+            ))
+            .schema(Some(ObjectBuilder::new().schema_type(Type::String)))
+            .build()]
+    }
+}
+
+/// A task rendered as JSON along with its `ETag` header.
+#[derive(Responder)]
+pub struct TaskResponse {
+    task: Json<Task>,
+    etag: Header<'static>,
+}
+
+impl From<Task> for TaskResponse {
+    fn from(task: Task) -> Self {
+        TaskResponse {
+            etag: Header::new("ETag", task.etag()),
+            task: Json(task),
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use crate::config::FrontendConfig;
+use crate::logging::traced;
+use rocket::fairing::AdHoc;
+use rocket::fs::{FileServer, NamedFile};
+use rocket::http::{Header, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::{self, Responder};
+use rocket::route::{self, Handler, Route};
+use rocket::{Build, Data, Request, Rocket, State};
+use std::path::PathBuf;
+
+#[cfg(feature = "embed-client")]
+use rocket::http::{ContentType, Method};
+#[cfg(feature = "embed-client")]
+use std::borrow::Cow;
+
+/// Rank of the routes serving client files. API routes all rank lower, so
+/// they are tried first, and `index.html` for client-side routes ranks
+/// higher, so it is tried last.
+const FILES_RANK: isize = 10;
+
+/// Trunk puts a hash of the contents in these file names, so they never change.
+const HASHED_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Everything else, `index.html` in particular, is checked on every load.
+const DEFAULT_CACHE_CONTROL: &str = "no-cache";
+
+/// The client files compiled into the binary by the `embed-client` feature.
+#[cfg(feature = "embed-client")]
+#[derive(rust_embed::RustEmbed)]
+#[folder = "../client/dist"]
+struct Embedded;
+
+/// Where the client files are read from.
+enum ClientFiles {
+    /// A Trunk `dist` directory on disk.
+    #[cfg_attr(feature = "embed-client", allow(dead_code))]
+    Dir(PathBuf),
+    /// The files compiled into the binary.
+    #[cfg(feature = "embed-client")]
+    Embedded,
+}
+
+/// A client file ready to be sent.
+enum Asset {
+    File(NamedFile),
+    #[cfg(feature = "embed-client")]
+    Embedded(ContentType, Cow<'static, [u8]>),
+}
+
+impl<'r> Responder<'r, 'static> for Asset {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        match self {
+            Asset::File(file) => file.respond_to(req),
+            #[cfg(feature = "embed-client")]
+            Asset::Embedded(content_type, data) => (content_type, data).respond_to(req),
+        }
+    }
+}
+
+#[cfg(feature = "embed-client")]
+impl Asset {
+    /// Look up a file compiled into the binary.
+    fn embedded(path: &str) -> Option<Asset> {
+        let file = Embedded::get(path)?;
+        let content_type = path
+            .rsplit_once('.')
+            .and_then(|(_, ext)| ContentType::from_extension(ext))
+            .unwrap_or(ContentType::Binary);
+        Some(Asset::Embedded(content_type, file.data))
+    }
+}
+
+impl ClientFiles {
+    /// The files compiled into the binary, unless serving them is disabled.
+    #[cfg(feature = "embed-client")]
+    fn locate(config: &FrontendConfig) -> Option<ClientFiles> {
+        config.enabled.then_some(ClientFiles::Embedded)
+    }
+
+    /// The files in the configured directory, or `None` if serving them is
+    /// disabled or there is nothing to serve.
+    #[cfg(not(feature = "embed-client"))]
+    fn locate(config: &FrontendConfig) -> Option<ClientFiles> {
+        if !config.enabled {
+            None
+        } else if config.dir.join("index.html").is_file() {
+            Some(ClientFiles::Dir(config.dir.clone()))
+        } else {
+            tracing::warn!(
+                dir = %config.dir.display(),
+                "not serving the client, there is no index.html in its directory"
+            );
+            None
+        }
+    }
+
+    /// The routes serving the files themselves.
+    fn routes(&self) -> Vec<Route> {
+        match self {
+            ClientFiles::Dir(dir) => FileServer::from(dir).rank(FILES_RANK).into(),
+            #[cfg(feature = "embed-client")]
+            ClientFiles::Embedded => vec![Route::ranked(
+                FILES_RANK,
+                Method::Get,
+                "/<path..>",
+                EmbeddedFiles,
+            )],
+        }
+    }
+
+    async fn index(&self) -> Option<Asset> {
+        match self {
+            ClientFiles::Dir(dir) => NamedFile::open(dir.join("index.html"))
+                .await
+                .ok()
+                .map(Asset::File),
+            #[cfg(feature = "embed-client")]
+            ClientFiles::Embedded => Asset::embedded("index.html"),
+        }
+    }
+}
+
+/// Serves the embedded files, forwarding requests for files that do not
+/// exist just like [`FileServer`] does.
+#[cfg(feature = "embed-client")]
+#[derive(Clone)]
+struct EmbeddedFiles;
+
+#[cfg(feature = "embed-client")]
+#[rocket::async_trait]
+impl Handler for EmbeddedFiles {
+    async fn handle<'r>(&self, req: &'r Request<'_>, data: Data<'r>) -> route::Outcome<'r> {
+        // Rejects `..`, hidden files and other paths that could escape the client
+        let path = req.segments::<PathBuf>(0..).ok();
+        let name = match path.as_ref().and_then(|path| path.to_str()) {
+            Some("") => "index.html".to_string(),
+            Some(path) => path.replace('\\', "/"),
+            None => return route::Outcome::forward(data, Status::NotFound),
+        };
+
+        match Asset::embedded(&name) {
+            Some(asset) => route::Outcome::from(req, asset),
+            None => route::Outcome::forward(data, Status::NotFound),
+        }
+    }
+}
+
+/// Whether Trunk put a content hash in the file name at the end of `path`,
+/// as in `client-4f5e3b2a1c0d9e8f_bg.wasm`.
+fn is_hashed(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.split('-').skip(1).any(|part| {
+        let hex = part.bytes().take_while(u8::is_ascii_hexdigit).count();
+        hex >= 16 && matches!(part.as_bytes().get(hex), Some(b'.' | b'_'))
+    })
+}
+
+/// A route handler that adds a `Cache-Control` header to client files.
+#[derive(Clone)]
+struct CacheControl(Box<dyn Handler>);
+
+#[rocket::async_trait]
+impl Handler for CacheControl {
+    async fn handle<'r>(&self, req: &'r Request<'_>, data: Data<'r>) -> route::Outcome<'r> {
+        let mut outcome = self.0.handle(req, data).await;
+        if let route::Outcome::Success(response) = &mut outcome {
+            let value = if is_hashed(req.uri().path().as_str()) {
+                HASHED_CACHE_CONTROL
+            } else {
+                DEFAULT_CACHE_CONTROL
+            };
+            response.set_header(Header::new("Cache-Control", value));
+        }
+        outcome
+    }
+}
+
+/// A path the client routes itself, so it gets `index.html`.
+///
+/// Paths below the first segment of any other route, such as `/api` or
+/// `/task`, are left to those routes, and paths that look like files are
+/// left missing, so that typos and stale links fail instead of returning
+/// the client.
+struct ClientRoute;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientRoute {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let path = req.uri().path();
+        let first = path.segments().next().unwrap_or("");
+        let last = path.segments().last().unwrap_or("");
+
+        let reserved = req
+            .rocket()
+            .routes()
+            .filter(|route| route.rank < FILES_RANK)
+            .filter_map(|route| route.uri.path().split('/').find(|s| !s.is_empty()))
+            .any(|segment| segment == first && !segment.starts_with('<'));
+
+        if reserved || last.contains('.') {
+            Outcome::Forward(Status::NotFound)
+        } else {
+            Outcome::Success(ClientRoute)
+        }
+    }
+}
+
+/// Serves `index.html` for client-side routes, after the files were tried.
+#[get("/<_..>", rank = 20)]
+async fn client_route(_route: ClientRoute, files: &State<ClientFiles>) -> Option<Asset> {
+    files.index().await
+}
+
+/// A fairing that serves the compiled client next to the API, when there is
+/// one to serve.
+///
+/// Files are served at the root. Other paths that are not claimed by an API
+/// route get `index.html`, so the client can handle them.
+pub fn frontend_fairing(config: FrontendConfig) -> AdHoc {
+    AdHoc::on_ignite("Client Files", |rocket: Rocket<Build>| async move {
+        let Some(files) = ClientFiles::locate(&config) else {
+            return rocket;
+        };
+
+        let routes: Vec<Route> = files
+            .routes()
+            .into_iter()
+            .chain(routes![client_route])
+            .map(|mut route| {
+                route.handler = Box::new(CacheControl(route.handler));
+                route
+            })
+            .collect();
+
+        rocket.mount("/", traced(routes)).manage(files)
+    })
+}
@@ -0,0 +1,63 @@
+use crate::database::{StorageBackend, TaskStore};
+use crate::error::ApiError;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// The body returned by `/health` and `/ready` when all is well.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HealthStatus {
+    pub status: String,
+}
+
+/// The body returned by `/version`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VersionInfo {
+    /// The version of the server crate.
+    pub version: String,
+    /// The commit the server was built from, if it was built from a git checkout.
+    pub git_sha: Option<String>,
+    /// The storage backend tasks are kept in.
+    pub storage: StorageBackend,
+}
+
+/// Liveness: answers as long as the server is handling requests.
+#[get("/health")]
+fn health() -> Json<HealthStatus> {
+    Json(HealthStatus {
+        status: String::from("ok"),
+    })
+}
+
+/// Readiness: checks that the store can be read from and written to.
+#[get("/ready")]
+fn ready(store: &State<Arc<dyn TaskStore>>) -> Result<Json<HealthStatus>, ApiError> {
+    store.check().map_err(|err| {
+        ApiError::new(
+            Status::ServiceUnavailable,
+            "not_ready",
+            format!("the task store is not available: {}", err),
+        )
+    })?;
+
+    Ok(Json(HealthStatus {
+        status: String::from("ready"),
+    }))
+}
+
+/// The build the server is running.
+#[get("/version")]
+fn version(store: &State<Arc<dyn TaskStore>>) -> Json<VersionInfo> {
+    Json(VersionInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_sha: option_env!("GIT_SHA").map(ToString::to_string),
+        storage: store.backend(),
+    })
+}
+
+/// Health check routes, mounted next to [`all_routes`](crate::all_routes).
+pub fn health_routes() -> Vec<rocket::Route> {
+    routes![health, ready, version]
+}
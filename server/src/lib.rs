@@ -1,12 +1,76 @@
 #[macro_use]
 extern crate rocket;
 
+mod config;
 mod cors;
 mod database;
-mod models;
+mod error;
+mod etag;
+mod frontend;
+mod health;
+mod logging;
+mod metrics;
+mod openapi;
+mod query;
 mod routes;
+mod shutdown;
 
-pub use crate::cors::config_cors;
-pub use crate::database::setup_database;
-pub use crate::models::Task;
-pub use crate::routes::all_routes;
+use rocket::figment::providers::Serialized;
+use rocket::{Build, Rocket};
+
+pub use crate::config::{
+    AppConfig, ConfigError, CorsConfig, FrontendConfig, LogConfig, LogFormat, MetricsConfig,
+    SledConfig,
+};
+pub use crate::cors::{config_cors, cors_options};
+pub use crate::database::{
+    open_store, setup_database, store_fairing, MemoryStore, OpenError, SledStore, StorageBackend,
+    StoreError, StoreResult, StoreStats, TaskStore,
+};
+#[cfg(feature = "sqlite")]
+pub use crate::database::{setup_sqlite_database, SqliteStore};
+pub use crate::error::{all_catchers, ApiError};
+pub use crate::etag::{IfMatch, TaskResponse};
+pub use crate::frontend::frontend_fairing;
+pub use crate::health::{health_routes, HealthStatus, VersionInfo};
+pub use crate::logging::{init_logging, traced, RequestId, RequestLogger, REQUEST_ID_HEADER};
+pub use crate::metrics::{metrics_routes, Metrics, MetricsAccess};
+pub use crate::openapi::{openapi_routes, ApiDoc, DOCS_PATH, OPENAPI_PATH};
+pub use crate::query::{Cursor, SortField, SortOrder, TaskQuery, DEFAULT_LIMIT, MAX_LIMIT};
+pub use crate::routes::{all_routes, legacy_routes, ApiVersion, API_VERSIONS};
+pub use crate::shutdown::FlushOnShutdown;
+pub use common::{paths, ErrorBody, ErrorDetail, Task, TaskPage, TaskPatch, MAX_DESCRIPTION_LEN};
+
+/// Build the Rocket instance served by every entry point.
+///
+/// Rocket is configured from the same sources as `config`, with the values in
+/// `config` taking precedence, so the listen address and port come from it.
+/// Fails if the CORS rules in `config` cannot be used. The store is opened
+/// when Rocket ignites, which fails if it cannot be opened.
+pub fn build_rocket(config: AppConfig) -> Result<Rocket<Build>, ConfigError> {
+    let cors = config_cors(&config.cors)?;
+    let figment = AppConfig::figment().merge(Serialized::globals(&config));
+    let metrics = config
+        .metrics
+        .enabled
+        .then(|| Metrics::new(&config.metrics));
+
+    let mut rocket = rocket::custom(figment)
+        .mount("/", traced(all_routes()))
+        .mount("/", traced(health_routes()))
+        .mount("/", traced(openapi_routes()))
+        .register("/", all_catchers())
+        .attach(RequestLogger)
+        .attach(cors)
+        .attach(frontend_fairing(config.frontend.clone()))
+        .attach(store_fairing(config))
+        .attach(FlushOnShutdown::default());
+    if let Some(metrics) = metrics {
+        rocket = rocket
+            .mount("/", traced(metrics_routes()))
+            .manage(metrics.clone())
+            .attach(metrics);
+    }
+
+    Ok(rocket)
+}
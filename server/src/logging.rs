@@ -0,0 +1,181 @@
+use crate::config::{ConfigError, LogConfig, LogFormat};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::request::{FromRequest, Outcome};
+use rocket::route::{self, Handler, Route};
+use rocket::{Data, Request, Response};
+use std::convert::Infallible;
+use std::time::Instant;
+use tracing::Instrument;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// The header a request id is read from and echoed back in.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Incoming request ids longer than this are replaced rather than logged.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Install the global `tracing` subscriber described by the configuration.
+///
+/// Rocket's own log messages are forwarded to it as well. If a subscriber
+/// has already been installed, e.g. by an application embedding the server,
+/// that one is kept.
+pub fn init_logging(config: &LogConfig) -> Result<(), ConfigError> {
+    let filter = EnvFilter::try_new(&config.filter)
+        .map_err(|err| ConfigError::invalid("logging.filter", err.to_string()))?;
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+
+    let _ = match config.format {
+        LogFormat::Pretty => builder.pretty().try_init(),
+        LogFormat::Json => builder
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .try_init(),
+    };
+
+    Ok(())
+}
+
+/// The id of a request, from its `X-Request-Id` header if it sent a usable
+/// one and freshly generated otherwise.
+///
+/// Every log line written while handling the request carries it, and it is
+/// returned in the response's `X-Request-Id` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// The id of `req`, assigned the first time it is asked for.
+    pub fn of<'r>(req: &'r Request<'_>) -> &'r RequestId {
+        req.local_cache(|| {
+            let incoming = req
+                .headers()
+                .get_one(REQUEST_ID_HEADER)
+                .map(str::trim)
+                .filter(|id| {
+                    !id.is_empty()
+                        && id.len() <= MAX_REQUEST_ID_LEN
+                        && id.bytes().all(|b| b.is_ascii_graphic())
+                });
+            match incoming {
+                Some(id) => RequestId(id.to_string()),
+                None => RequestId(Uuid::new_v4().to_string()),
+            }
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r RequestId {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(RequestId::of(req))
+    }
+}
+
+/// When the request logger first saw a request.
+struct RequestStart(Instant);
+
+/// A fairing that assigns every request an id and logs one line per
+/// response with its method, path, status and latency.
+#[derive(Default)]
+pub struct RequestLogger;
+
+#[rocket::async_trait]
+impl Fairing for RequestLogger {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request Logger",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        req.local_cache(|| RequestStart(Instant::now()));
+        RequestId::of(req);
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let start = req.local_cache(|| RequestStart(Instant::now()));
+        let request_id = RequestId::of(req);
+        res.set_header(Header::new(REQUEST_ID_HEADER, request_id.0.clone()));
+
+        let status = res.status().code;
+        let latency_ms = start.0.elapsed().as_secs_f64() * 1000.0;
+        if res.status().class().is_server_error() {
+            tracing::warn!(
+                request_id = %request_id.0,
+                method = %req.method(),
+                path = %req.uri().path(),
+                status,
+                latency_ms,
+                "request failed"
+            );
+        } else {
+            tracing::info!(
+                request_id = %request_id.0,
+                method = %req.method(),
+                path = %req.uri().path(),
+                status,
+                latency_ms,
+                "request handled"
+            );
+        }
+    }
+}
+
+/// The route that answered a request, as recorded by [`traced`] handlers.
+///
+/// Unlike [`Request::route`], which is left pointing at the last route
+/// tried, it is only set by a route that did not forward the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedRoute(pub Option<String>);
+
+impl MatchedRoute {
+    /// The path of the route that answered `req`, e.g. `/task/<id>`, if any.
+    pub fn of<'r>(req: &'r Request<'_>) -> Option<&'r str> {
+        req.local_cache(|| MatchedRoute(None)).0.as_deref()
+    }
+}
+
+/// A route handler that runs inside a span carrying the request id, so
+/// events logged by the handler and the store can be traced to the request.
+/// It also records the route as the request's [`MatchedRoute`] unless the
+/// handler forwards.
+#[derive(Clone)]
+struct Traced(Box<dyn Handler>);
+
+#[rocket::async_trait]
+impl Handler for Traced {
+    async fn handle<'r>(&self, req: &'r Request<'_>, data: Data<'r>) -> route::Outcome<'r> {
+        let span = tracing::info_span!(
+            "request",
+            request_id = %RequestId::of(req).0,
+            method = %req.method(),
+            path = %req.uri().path(),
+        );
+        let outcome = self.0.handle(req, data).instrument(span).await;
+        if !matches!(outcome, route::Outcome::Forward(_)) {
+            if let Some(route) = req.route() {
+                req.local_cache(|| MatchedRoute(Some(route.uri.path().to_string())));
+            }
+        }
+        outcome
+    }
+}
+
+/// Run the handlers of `routes` inside a span per request, recording the
+/// route that answers as the request's [`MatchedRoute`].
+pub fn traced(routes: Vec<Route>) -> Vec<Route> {
+    routes
+        .into_iter()
+        .map(|mut route| {
+            route.handler = Box::new(Traced(route.handler));
+            route
+        })
+        .collect()
+}
@@ -0,0 +1,190 @@
+use crate::config::MetricsConfig;
+use crate::database::TaskStore;
+use crate::logging::MatchedRoute;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Data, Request, Response, State};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The route label of requests that did not match any route, so that
+/// scanners probing random paths cannot create unbounded label values.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Request and storage metrics, served in Prometheus text format at `/metrics`.
+///
+/// Attached as a fairing, it counts every response by method, route and
+/// status and records how long the request took. Requests are labelled with
+/// the route that answered them, e.g. `/task/<id>`, rather than the path
+/// requested, which only works for routes mounted through [`traced`](crate::traced).
+/// The task gauges are read from the store whenever metrics are scraped.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    latency: HistogramVec,
+    tasks: IntGauge,
+    completed: IntGauge,
+    size_on_disk: IntGauge,
+    token: Option<Arc<str>>,
+}
+
+/// When the fairing first saw a request.
+struct RequestStart(Instant);
+
+impl Metrics {
+    pub fn new(config: &MetricsConfig) -> Self {
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled."),
+            &["method", "route", "status"],
+        )
+        .expect("valid counter");
+        let latency = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to handle HTTP requests.",
+            ),
+            &["method", "route"],
+        )
+        .expect("valid histogram");
+        let tasks = IntGauge::new("tasks", "Tasks in the store.").expect("valid gauge");
+        let completed =
+            IntGauge::new("tasks_completed", "Completed tasks in the store.").expect("valid gauge");
+        // Stays at 0 for stores that are not kept on disk
+        let size_on_disk = IntGauge::new(
+            "store_size_on_disk_bytes",
+            "Bytes the task store takes up on disk.",
+        )
+        .expect("valid gauge");
+
+        let registry = Registry::new();
+        registry
+            .register(Box::new(requests.clone()))
+            .and_then(|_| registry.register(Box::new(latency.clone())))
+            .and_then(|_| registry.register(Box::new(tasks.clone())))
+            .and_then(|_| registry.register(Box::new(completed.clone())))
+            .and_then(|_| registry.register(Box::new(size_on_disk.clone())))
+            .expect("metric names are unique");
+
+        Metrics {
+            registry,
+            requests,
+            latency,
+            tasks,
+            completed,
+            size_on_disk,
+            token: config.token.as_deref().map(Arc::from),
+        }
+    }
+
+    /// Refresh the task gauges and render every metric in text format.
+    fn render(&self, store: &dyn TaskStore) -> String {
+        match store.stats() {
+            Ok(stats) => {
+                self.tasks.set(gauge_value(stats.tasks));
+                self.completed.set(gauge_value(stats.completed));
+                self.size_on_disk
+                    .set(gauge_value(stats.size_on_disk.unwrap_or(0)));
+            }
+            // Keep serving the request metrics, which matter most when the store is failing
+            Err(err) => tracing::warn!(error = %err, "could not read task store stats"),
+        }
+
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("metrics encode as text");
+        String::from_utf8(buffer).expect("text format is UTF-8")
+    }
+}
+
+/// Saturate counts that do not fit in a gauge.
+fn gauge_value(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+#[rocket::async_trait]
+impl Fairing for Metrics {
+    fn info(&self) -> Info {
+        Info {
+            name: "Prometheus Metrics",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        req.local_cache(|| RequestStart(Instant::now()));
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let start = req.local_cache(|| RequestStart(Instant::now()));
+        let method = req.method().as_str();
+        let route = MatchedRoute::of(req).unwrap_or(UNMATCHED_ROUTE);
+
+        self.requests
+            .with_label_values(&[method, route, res.status().code.to_string().as_str()])
+            .inc();
+        self.latency
+            .with_label_values(&[method, route])
+            .observe(start.0.elapsed().as_secs_f64());
+    }
+}
+
+/// Access to `/metrics`, granted to every request when no token is
+/// configured and otherwise only to `Authorization: Bearer <token>`.
+pub struct MetricsAccess;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for MetricsAccess {
+    type Error = &'static str;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(metrics) = req.rocket().state::<Metrics>() else {
+            return Outcome::Error((Status::NotFound, "metrics are disabled"));
+        };
+        let Some(token) = metrics.token.as_deref() else {
+            return Outcome::Success(MetricsAccess);
+        };
+
+        let given = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match given {
+            Some(given) if tokens_match(given.trim(), token) => Outcome::Success(MetricsAccess),
+            _ => Outcome::Error((Status::Unauthorized, "missing or wrong metrics token")),
+        }
+    }
+}
+
+/// Compare tokens without returning early, so timing does not reveal how
+/// much of a guess was right.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[get("/metrics")]
+fn metrics(
+    _access: MetricsAccess,
+    metrics: &State<Metrics>,
+    store: &State<Arc<dyn TaskStore>>,
+) -> (ContentType, String) {
+    let content_type = ContentType::parse_flexible(prometheus::TEXT_FORMAT)
+        .expect("valid Prometheus content type");
+    (content_type, metrics.render(store.as_ref()))
+}
+
+/// The `/metrics` route, mounted by [`build_rocket`](crate::build_rocket)
+/// together with the [`Metrics`] fairing when metrics are enabled.
+pub fn metrics_routes() -> Vec<rocket::Route> {
+    routes![metrics]
+}
@@ -0,0 +1,65 @@
+use crate::query::{SortField, SortOrder};
+use crate::routes::v1;
+use common::{ErrorBody, ErrorDetail, Task, TaskPage, TaskPatch};
+use rocket::serde::json::Json;
+use utoipa::OpenApi;
+use utoipa_rapidoc::RapiDoc;
+
+/// Where the OpenAPI document is served.
+pub const OPENAPI_PATH: &str = "/openapi.json";
+
+/// Where the interactive API documentation is served.
+pub const DOCS_PATH: &str = "/docs";
+
+/// The OpenAPI document for the task API, generated from the routes in
+/// [`all_routes`](crate::all_routes) and the types in `common`.
+///
+/// Each API version is described by its own document nested under the
+/// version's base path. The deprecated root aliases are left out. A route
+/// added to a version must also be listed in that version's `paths`, which
+/// the tests check.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Tasks API"),
+    nest((path = "/api/v1", api = V1Doc)),
+    tags((name = "tasks", description = "Create, read, update and delete tasks")),
+)]
+pub struct ApiDoc;
+
+/// Version 1 of the task API, relative to [`paths::API_V1`](common::paths::API_V1).
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        v1::create_task,
+        v1::get_task,
+        v1::get_tasks,
+        v1::update_all_tasks,
+        v1::update_task,
+        v1::patch_task,
+        v1::delete_task,
+    ),
+    components(schemas(
+        Task,
+        TaskPage,
+        TaskPatch,
+        ErrorBody,
+        ErrorDetail,
+        SortField,
+        SortOrder
+    ))
+)]
+struct V1Doc;
+
+#[get("/openapi.json")]
+fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// The OpenAPI document and a RapiDoc UI rendering it.
+pub fn openapi_routes() -> Vec<rocket::Route> {
+    let mut routes = routes![openapi_json];
+    routes.extend(Vec::<rocket::Route>::from(
+        RapiDoc::new(OPENAPI_PATH).path(DOCS_PATH),
+    ));
+    routes
+}
@@ -0,0 +1,245 @@
+use crate::database::{StoreError, StoreResult};
+use common::{Task, TaskPage};
+use rocket::form::FromFormField;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use utoipa::{IntoParams, ToSchema};
+
+/// Number of tasks returned per page when no `limit` is given.
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// Largest page a client can ask for.
+pub const MAX_LIMIT: usize = 500;
+
+/// The field tasks are sorted by.
+#[derive(FromFormField, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[schema(rename_all = "lowercase")]
+pub enum SortField {
+    #[default]
+    Id,
+    Description,
+    Completed,
+}
+
+/// The direction tasks are sorted in.
+#[derive(FromFormField, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[schema(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// The sort key of the last task on a page.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum SortKey {
+    Id,
+    Description(String),
+    Completed(bool),
+}
+
+/// Where the next page starts: the sort key and id of the last task returned.
+///
+/// Sent to clients as an opaque hex string in `next_cursor` and read back
+/// from the `after` query parameter.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Cursor {
+    id: u64,
+    key: SortKey,
+}
+
+impl Cursor {
+    fn new(task: &Task, sort: SortField) -> Self {
+        let key = match sort {
+            SortField::Id => SortKey::Id,
+            SortField::Description => SortKey::Description(task.description.clone()),
+            SortField::Completed => SortKey::Completed(task.completed),
+        };
+        Cursor { id: task.id, key }
+    }
+
+    /// The id of the last task returned.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The field the cursor was issued for.
+    fn sort_field(&self) -> SortField {
+        match self.key {
+            SortKey::Id => SortField::Id,
+            SortKey::Description(_) => SortField::Description,
+            SortKey::Completed(_) => SortField::Completed,
+        }
+    }
+
+    /// The sort value of the last task returned, as an SQLite value.
+    ///
+    /// `None` for cursors sorted by id, where the id alone is the position.
+    #[cfg(feature = "sqlite")]
+    pub fn sort_value(&self) -> Option<rusqlite::types::Value> {
+        match &self.key {
+            SortKey::Id => None,
+            SortKey::Description(description) => Some(description.clone().into()),
+            SortKey::Completed(completed) => Some((*completed).into()),
+        }
+    }
+
+    fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        json.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    fn decode(value: &str) -> Option<Self> {
+        if !value.len().is_multiple_of(2) {
+            return None;
+        }
+        let bytes = (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+/// Filtering, sorting and pagination options for listing tasks.
+///
+/// Parsed from the query string of `GET /tasks`, e.g.
+/// `?completed=false&q=milk&sort=description&order=desc&limit=20&after=...`.
+#[derive(FromForm, IntoParams, Debug, Clone, Default)]
+#[into_params(parameter_in = Query)]
+pub struct TaskQuery {
+    /// Only return tasks with this completion state.
+    pub completed: Option<bool>,
+    /// Only return tasks whose description contains this text, ignoring case.
+    pub q: Option<String>,
+    /// The field to sort by.
+    // Not `default`, whose generated `.into()` trips `useless_conversion`, and
+    // not an `Option`, which Rocket sets to `None` for unknown values.
+    #[field(default_with = Some(SortField::Id))]
+    #[param(inline, required = false)]
+    pub sort: SortField,
+    /// The direction to sort in.
+    #[field(default_with = Some(SortOrder::Asc))]
+    #[param(inline, required = false)]
+    pub order: SortOrder,
+    /// Page size, 100 by default and capped at 500.
+    #[param(minimum = 1, maximum = 500)]
+    pub limit: Option<usize>,
+    /// The `next_cursor` of the previous page.
+    ///
+    /// Kept as a string because Rocket turns unparsable optional fields into
+    /// `None`, which would silently restart from the first page.
+    pub after: Option<String>,
+}
+
+impl TaskQuery {
+    /// The number of tasks on a page.
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    /// Decode the `after` cursor and check it was issued for the same sort field.
+    pub fn cursor(&self) -> StoreResult<Option<Cursor>> {
+        let Some(after) = &self.after else {
+            return Ok(None);
+        };
+        let cursor = Cursor::decode(after)
+            .ok_or_else(|| StoreError::Invalid(String::from("the cursor is malformed")))?;
+        if cursor.sort_field() != self.sort {
+            return Err(StoreError::Invalid(String::from(
+                "the cursor was issued for a different sort field",
+            )));
+        }
+
+        Ok(Some(cursor))
+    }
+
+    /// Whether a task passes the `completed` and `q` filters.
+    pub fn matches(&self, task: &Task) -> bool {
+        if self
+            .completed
+            .is_some_and(|completed| completed != task.completed)
+        {
+            return false;
+        }
+        match &self.q {
+            Some(q) => task.description.to_lowercase().contains(&q.to_lowercase()),
+            None => true,
+        }
+    }
+
+    /// Order two tasks by the sort field and direction, breaking ties by id.
+    pub fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        let ordering = match self.sort {
+            SortField::Id => Ordering::Equal,
+            SortField::Description => a.description.cmp(&b.description),
+            SortField::Completed => a.completed.cmp(&b.completed),
+        }
+        .then(a.id.cmp(&b.id));
+
+        match self.order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    }
+
+    /// Whether a task sorts strictly after the cursor.
+    fn is_after_cursor(&self, task: &Task, cursor: Option<&Cursor>) -> bool {
+        let Some(cursor) = cursor else {
+            return true;
+        };
+        // An empty task stands in for the last one on the previous page
+        let mut last = Task::new("", false);
+        last.id = cursor.id;
+        match &cursor.key {
+            SortKey::Id => {}
+            SortKey::Description(description) => last.description = description.clone(),
+            SortKey::Completed(completed) => last.completed = *completed,
+        }
+        self.compare(task, &last) == Ordering::Greater
+    }
+
+    /// Build a page from tasks that are already in query order.
+    ///
+    /// Only consumes as many tasks as needed to fill the page, so stores can
+    /// pass a lazy range scan.
+    pub fn page_sorted<I>(&self, tasks: I) -> StoreResult<TaskPage>
+    where
+        I: IntoIterator<Item = StoreResult<Task>>,
+    {
+        let cursor = self.cursor()?;
+        let limit = self.limit();
+        let mut items = Vec::with_capacity(limit);
+        let mut has_more = false;
+
+        for task in tasks {
+            let task = task?;
+            if !self.matches(&task) || !self.is_after_cursor(&task, cursor.as_ref()) {
+                continue;
+            }
+            if items.len() == limit {
+                has_more = true;
+                break;
+            }
+            items.push(task);
+        }
+
+        let next_cursor = match items.last() {
+            Some(last) if has_more => Some(Cursor::new(last, self.sort).encode()),
+            _ => None,
+        };
+
+        Ok(TaskPage { items, next_cursor })
+    }
+
+    /// Build a page from tasks in any order.
+    pub fn page_unsorted(&self, tasks: Vec<Task>) -> StoreResult<TaskPage> {
+        let mut tasks: Vec<Task> = tasks
+            .into_iter()
+            .filter(|task| self.matches(task))
+            .collect();
+        tasks.sort_by(|a, b| self.compare(a, b));
+        self.page_sorted(tasks.into_iter().map(Ok))
+    }
+}
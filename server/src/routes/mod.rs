@@ -0,0 +1,98 @@
+//! The task API, one module per version.
+//!
+//! Each version is mounted under its own base path and keeps its own request
+//! and response types, while every version talks to the same [`TaskStore`].
+//! A new version gets a module with its routes and DTOs, converting between
+//! them and the stored `Task`, and an entry in [`API_VERSIONS`].
+//!
+//! [`TaskStore`]: crate::TaskStore
+
+pub(crate) mod v1;
+
+use common::paths;
+use rocket::http::Header;
+use rocket::route::{self, Handler, Route};
+use rocket::{Data, Request};
+
+/// One version of the task API and where it is mounted.
+pub struct ApiVersion {
+    /// The base path the version is mounted under, e.g. `/api/v1`.
+    pub base: &'static str,
+    /// The routes of the version, relative to `base`.
+    pub routes: fn() -> Vec<Route>,
+}
+
+impl ApiVersion {
+    /// The routes of this version with `base` prepended to their paths.
+    pub fn mounted(&self) -> Vec<Route> {
+        (self.routes)()
+            .into_iter()
+            .map(|route| {
+                route
+                    .map_base(|base| format!("{}{}", self.base, base))
+                    .expect("API base paths are valid")
+            })
+            .collect()
+    }
+}
+
+/// Every version of the task API that is served, oldest first.
+pub const API_VERSIONS: &[ApiVersion] = &[ApiVersion {
+    base: paths::API_V1,
+    routes: v1::routes,
+}];
+
+/// The version the unversioned root paths are aliases of.
+const LEGACY_VERSION: &ApiVersion = &API_VERSIONS[0];
+
+/// A route handler for a deprecated alias, which marks its responses with a
+/// `Deprecation` header and links to the same path in the current version.
+#[derive(Clone)]
+struct Deprecated {
+    handler: Box<dyn Handler>,
+    successor: &'static str,
+}
+
+#[rocket::async_trait]
+impl Handler for Deprecated {
+    async fn handle<'r>(&self, req: &'r Request<'_>, data: Data<'r>) -> route::Outcome<'r> {
+        let mut outcome = self.handler.handle(req, data).await;
+        if let route::Outcome::Success(response) = &mut outcome {
+            response.set_header(Header::new("Deprecation", "true"));
+            response.set_header(Header::new(
+                "Link",
+                format!(
+                    "<{}{}>; rel=\"successor-version\"",
+                    self.successor,
+                    req.uri().path()
+                ),
+            ));
+        }
+        outcome
+    }
+}
+
+/// The routes of the legacy version at the root, kept for clients written
+/// before the API was versioned.
+pub fn legacy_routes() -> Vec<Route> {
+    (LEGACY_VERSION.routes)()
+        .into_iter()
+        .map(|mut route| {
+            route.handler = Box::new(Deprecated {
+                handler: route.handler,
+                successor: LEGACY_VERSION.base,
+            });
+            route
+        })
+        .collect()
+}
+
+/// Every API route: each version under its base path, followed by the
+/// deprecated root aliases.
+pub fn all_routes() -> Vec<Route> {
+    API_VERSIONS
+        .iter()
+        .flat_map(ApiVersion::mounted)
+        .chain(legacy_routes())
+        .collect()
+}
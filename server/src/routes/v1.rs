@@ -0,0 +1,206 @@
+//! Version 1 of the task API, which exchanges the `common` types as they are.
+
+use crate::database::TaskStore;
+use crate::error::ApiError;
+use crate::etag::{IfMatch, TaskResponse};
+use crate::query::TaskQuery;
+use common::{paths, ErrorBody, ErrorDetail, Task, TaskPage, TaskPatch};
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::{Route, State};
+use std::sync::Arc;
+
+type ApiResult<T> = Result<T, ApiError>;
+
+/// Fail with every problem found if validation reported any.
+fn check(details: Vec<ErrorDetail>) -> ApiResult<()> {
+    if details.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::validation(details))
+    }
+}
+
+/// Create a new task. The database id and version will be automatically assigned.
+#[utoipa::path(
+    tag = "tasks",
+    request_body = Task,
+    responses(
+        (status = 201, description = "The created task", body = Task, headers(
+            ("Location" = String, description = "Where the task can be read"),
+            ("ETag" = String, description = "The task version, for `If-Match`"),
+        )),
+        (status = 400, description = "The task failed validation", body = ErrorBody),
+    )
+)]
+#[post("/task", format = "json", data = "<task>")]
+fn create_task(
+    route: &Route,
+    store: &State<Arc<dyn TaskStore>>,
+    task: Json<Task>,
+) -> ApiResult<status::Created<TaskResponse>> {
+    check(task.validate())?;
+
+    // Delegate the task creation to the store
+    let task = store.create(task.0)?;
+
+    // Point to the task below the base this route is mounted at
+    let base = route.uri.base().trim_end_matches('/');
+    let location = format!("{}{}", base, paths::task(task.id));
+    Ok(status::Created::new(location).body(task.into()))
+}
+
+/// Return a page of tasks, which may be empty.
+///
+/// Supports `completed` and `q` filters, `sort` and `order`, and cursor
+/// pagination through `limit` and `after`. See [`TaskQuery`].
+#[utoipa::path(
+    tag = "tasks",
+    params(TaskQuery),
+    responses(
+        (status = 200, description = "A page of tasks", body = TaskPage),
+        (status = 400, description = "The `after` cursor is invalid", body = ErrorBody),
+    )
+)]
+#[get("/tasks?<query..>")]
+fn get_tasks(store: &State<Arc<dyn TaskStore>>, query: TaskQuery) -> ApiResult<Json<TaskPage>> {
+    // Ask the store for the matching page
+    Ok(Json(store.list(&query)?))
+}
+
+/// Replace all tasks with a Vec<Task> in one atomic write.
+///
+/// Every task is validated first. If any is invalid nothing is written and
+/// the error lists the problems along with the position of each task.
+#[utoipa::path(
+    tag = "tasks",
+    request_body = Vec<Task>,
+    responses(
+        (status = 204, description = "The tasks were replaced"),
+        (status = 400, description = "A task failed validation", body = ErrorBody),
+    )
+)]
+#[post("/tasks", format = "application/json", data = "<tasks>")]
+fn update_all_tasks(
+    store: &State<Arc<dyn TaskStore>>,
+    tasks: Json<Vec<Task>>,
+) -> ApiResult<status::NoContent> {
+    check(
+        tasks
+            .iter()
+            .enumerate()
+            .flat_map(|(i, task)| task.validate().into_iter().map(move |detail| detail.at(i)))
+            .collect(),
+    )?;
+
+    store.replace_all(tasks.0)?;
+
+    Ok(status::NoContent)
+}
+
+/// Get a task by id, with its version in the `ETag` header.
+#[utoipa::path(
+    tag = "tasks",
+    responses(
+        (status = 200, description = "The task", body = Task, headers(
+            ("ETag" = String, description = "The task version, for `If-Match`"),
+        )),
+        (status = 404, description = "There is no task with this id", body = ErrorBody),
+    )
+)]
+#[get("/task/<id>")]
+fn get_task(store: &State<Arc<dyn TaskStore>>, id: u64) -> ApiResult<TaskResponse> {
+    Ok(store.get(id)?.into())
+}
+
+/// Update a task by id. Honors `If-Match` to avoid overwriting newer changes.
+#[utoipa::path(
+    tag = "tasks",
+    params(IfMatch),
+    request_body = Task,
+    responses(
+        (status = 200, description = "The updated task", body = Task, headers(
+            ("ETag" = String, description = "The new task version"),
+        )),
+        (status = 400, description = "The task failed validation", body = ErrorBody),
+        (status = 404, description = "There is no task with this id", body = ErrorBody),
+        (status = 412, description = "The task has changed since `If-Match`", body = ErrorBody),
+    )
+)]
+#[put("/task/<id>", format = "application/json", data = "<task>")]
+fn update_task(
+    store: &State<Arc<dyn TaskStore>>,
+    id: u64,
+    if_match: IfMatch,
+    task: Json<Task>,
+) -> ApiResult<TaskResponse> {
+    if task.id != id {
+        return Err(ApiError::bad_request(format!(
+            "task id {} does not match id {} in the path",
+            task.id, id
+        )));
+    }
+    check(task.validate())?;
+
+    Ok(store.update(id, task.0, if_match.versions()?)?.into())
+}
+
+/// Update some fields of a task by id and return the merged task. Honors `If-Match`.
+#[utoipa::path(
+    tag = "tasks",
+    params(IfMatch),
+    request_body = TaskPatch,
+    responses(
+        (status = 200, description = "The updated task", body = Task, headers(
+            ("ETag" = String, description = "The new task version"),
+        )),
+        (status = 400, description = "The patch failed validation", body = ErrorBody),
+        (status = 404, description = "There is no task with this id", body = ErrorBody),
+        (status = 412, description = "The task has changed since `If-Match`", body = ErrorBody),
+    )
+)]
+#[patch("/task/<id>", format = "application/json", data = "<patch>")]
+fn patch_task(
+    store: &State<Arc<dyn TaskStore>>,
+    id: u64,
+    if_match: IfMatch,
+    patch: Json<TaskPatch>,
+) -> ApiResult<TaskResponse> {
+    check(patch.validate())?;
+
+    Ok(store.patch(id, &patch, if_match.versions()?)?.into())
+}
+
+/// Delete a task by id. Honors `If-Match`.
+#[utoipa::path(
+    tag = "tasks",
+    params(IfMatch),
+    responses(
+        (status = 204, description = "The task was deleted"),
+        (status = 404, description = "There is no task with this id", body = ErrorBody),
+        (status = 412, description = "The task has changed since `If-Match`", body = ErrorBody),
+    )
+)]
+#[delete("/task/<id>")]
+fn delete_task(
+    store: &State<Arc<dyn TaskStore>>,
+    id: u64,
+    if_match: IfMatch,
+) -> ApiResult<status::NoContent> {
+    store.delete(id, if_match.versions()?)?;
+
+    Ok(status::NoContent)
+}
+
+/// The version 1 routes, relative to [`paths::API_V1`].
+pub fn routes() -> Vec<Route> {
+    routes![
+        create_task,
+        get_task,
+        get_tasks,
+        update_all_tasks,
+        update_task,
+        patch_task,
+        delete_task
+    ]
+}
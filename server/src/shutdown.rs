@@ -0,0 +1,68 @@
+use crate::database::TaskStore;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::tokio::sync::Notify;
+use rocket::tokio::time::{timeout, Duration};
+use rocket::{Data, Orbit, Request, Response, Rocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A fairing that flushes the task store when the server shuts down.
+///
+/// Shutdown fairings run while requests may still be in flight, so the
+/// fairing counts requests and waits for the last one to finish, or for the
+/// grace and mercy periods to run out, before flushing. Without it, writes
+/// made since the last background flush could be lost on `SIGTERM`.
+#[derive(Default)]
+pub struct FlushOnShutdown {
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+impl FlushOnShutdown {
+    /// Wait until no request is being handled.
+    async fn requests_finished(&self) {
+        loop {
+            let idle = self.idle.notified();
+            if self.in_flight.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for FlushOnShutdown {
+    fn info(&self) -> Info {
+        Info {
+            name: "Flush on Shutdown",
+            kind: Kind::Request | Kind::Response | Kind::Shutdown,
+        }
+    }
+
+    async fn on_request(&self, _req: &mut Request<'_>, _data: &mut Data<'_>) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+    }
+
+    async fn on_response<'r>(&self, _req: &'r Request<'_>, _res: &mut Response<'r>) {
+        if self.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+
+    async fn on_shutdown(&self, rocket: &Rocket<Orbit>) {
+        let shutdown = &rocket.config().shutdown;
+        let patience = Duration::from_secs(u64::from(shutdown.grace + shutdown.mercy));
+        if timeout(patience, self.requests_finished()).await.is_err() {
+            tracing::warn!("requests were still running at shutdown, flushing anyway");
+        }
+
+        let Some(store) = rocket.state::<Arc<dyn TaskStore>>() else {
+            return;
+        };
+        match store.flush().await {
+            Ok(bytes) => tracing::info!(bytes, "flushed the task store before shutdown"),
+            Err(err) => tracing::error!(error = %err, "could not flush the task store on shutdown"),
+        }
+    }
+}
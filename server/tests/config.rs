@@ -0,0 +1,178 @@
+// `Jail` closures have to return figment's error type, which is large.
+#![allow(clippy::result_large_err)]
+
+use rocket::figment::Jail;
+use server::{AppConfig, ConfigError, LogFormat, StorageBackend};
+
+/// The key of a validation error, or `None` if loading succeeded or failed to parse.
+fn invalid_key(result: Result<AppConfig, ConfigError>) -> Option<&'static str> {
+    match result {
+        Err(ConfigError::Invalid { key, .. }) => Some(key),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_config_defaults() {
+    Jail::expect_with(|_| {
+        assert_eq!(
+            AppConfig::load().expect("valid config"),
+            AppConfig::default()
+        );
+        Ok(())
+    });
+}
+
+#[test]
+fn test_config_layers() {
+    Jail::expect_with(|jail| {
+        jail.create_file(
+            "Rocket.toml",
+            r#"
+            [default]
+            storage = "memory"
+            database_path = "tasks.db"
+            port = 9000
+
+            [default.sled]
+            cache_capacity = 2000
+            flush_every_ms = 0
+
+            [default.cors]
+            allowed_origins = ["https://example.com"]
+            allow_credentials = true
+            "#,
+        )?;
+
+        let config = AppConfig::load().expect("valid config");
+        assert_eq!(config.storage, StorageBackend::Memory);
+        assert_eq!(config.database_path.to_str(), Some("tasks.db"));
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.sled.cache_capacity, 2000);
+        assert_eq!(config.sled.flush_every_ms, 0);
+        assert_eq!(config.cors.allowed_origins, vec!["https://example.com"]);
+        assert!(config.cors.allow_credentials);
+        // keys that are not set keep their defaults
+        assert_eq!(config.cors.allowed_headers, vec!["*"]);
+
+        // ROCKET_ variables override Rocket.toml and APP_ variables override both
+        jail.set_env("ROCKET_PORT", 9001);
+        jail.set_env("ROCKET_DATABASE_PATH", "rocket.db");
+        jail.set_env("APP_DATABASE_PATH", "app.db");
+        jail.set_env("APP_SLED__CACHE_CAPACITY", 5000);
+        jail.set_env("APP_CORS__ALLOWED_METHODS", r#"["GET", "POST"]"#);
+
+        let config = AppConfig::load().expect("valid config");
+        assert_eq!(config.port, 9001);
+        assert_eq!(config.database_path.to_str(), Some("app.db"));
+        assert_eq!(config.sled.cache_capacity, 5000);
+        assert_eq!(config.cors.allowed_methods, vec!["GET", "POST"]);
+        Ok(())
+    });
+}
+
+#[test]
+fn test_config_validation() {
+    Jail::expect_with(|jail| {
+        jail.set_env("APP_STORAGE", "postgres");
+        assert!(matches!(AppConfig::load(), Err(ConfigError::Parse(_))));
+        jail.set_env("APP_STORAGE", "sled");
+
+        jail.set_env("APP_LOGGING__FORMAT", "xml");
+        assert!(matches!(AppConfig::load(), Err(ConfigError::Parse(_))));
+        jail.set_env("APP_LOGGING__FORMAT", "json");
+        assert_eq!(
+            AppConfig::load().expect("valid config").logging.format,
+            LogFormat::Json
+        );
+
+        jail.set_env("APP_DATABASE_PATH", "");
+        assert_eq!(invalid_key(AppConfig::load()), Some("database_path"));
+        jail.set_env("APP_STORAGE", "memory");
+        assert!(AppConfig::load().is_ok());
+        Ok(())
+    });
+
+    // with an explicit allow-list so the checks on the other keys are reached
+    let origins = ("APP_CORS__ALLOWED_ORIGINS", r#"["https://example.com"]"#);
+    let cases: &[(&[(&str, &str)], &str)] = &[
+        (&[("APP_SLED__CACHE_CAPACITY", "0")], "sled.cache_capacity"),
+        (&[("APP_METRICS__TOKEN", r#""""#)], "metrics.token"),
+        (&[("APP_LOGGING__FILTER", "server=loud")], "logging.filter"),
+        (
+            &[("APP_CORS__ALLOWED_ORIGINS", "[]")],
+            "cors.allowed_origins",
+        ),
+        (
+            &[("APP_CORS__ALLOWED_ORIGINS", r#"["not an origin"]"#)],
+            "cors.allowed_origins",
+        ),
+        (
+            &[(
+                "APP_CORS__ALLOWED_ORIGINS",
+                r#"["*", "https://example.com"]"#,
+            )],
+            "cors.allowed_origins",
+        ),
+        (
+            &[(
+                "APP_CORS__ALLOWED_ORIGIN_PATTERNS",
+                r#"["^https://.+\\.example\\.com$"]"#,
+            )],
+            "cors.allowed_origins",
+        ),
+        (
+            &[
+                origins,
+                ("APP_CORS__ALLOWED_ORIGIN_PATTERNS", r#"["(unclosed"]"#),
+            ],
+            "cors.allowed_origin_patterns",
+        ),
+        (
+            &[("APP_CORS__ALLOWED_METHODS", "[]")],
+            "cors.allowed_methods",
+        ),
+        (
+            &[("APP_CORS__ALLOWED_METHODS", r#"["GET", "FETCH"]"#)],
+            "cors.allowed_methods",
+        ),
+        (
+            &[("APP_CORS__ALLOWED_HEADERS", r#"["Content Type"]"#)],
+            "cors.allowed_headers",
+        ),
+        (
+            &[("APP_CORS__EXPOSE_HEADERS", r#"["ETag", ""]"#)],
+            "cors.expose_headers",
+        ),
+        (
+            &[("APP_CORS__ALLOW_CREDENTIALS", "true")],
+            "cors.allow_credentials",
+        ),
+    ];
+    for (vars, key) in cases {
+        Jail::expect_with(|jail| {
+            for (name, value) in vars.iter() {
+                jail.set_env(name, value);
+            }
+            let result = AppConfig::load();
+            let message = result.as_ref().map_err(ToString::to_string).err();
+            assert_eq!(invalid_key(result), Some(*key), "{:?}", vars);
+            assert!(message.is_some_and(|message| message.contains(key)));
+            Ok(())
+        });
+    }
+
+    // exact origins, patterns and credentials can be combined
+    Jail::expect_with(|jail| {
+        jail.set_env(origins.0, origins.1);
+        jail.set_env(
+            "APP_CORS__ALLOWED_ORIGIN_PATTERNS",
+            r#"["^https://.+\\.example\\.com$"]"#,
+        );
+        jail.set_env("APP_CORS__ALLOW_CREDENTIALS", true);
+        jail.set_env("APP_CORS__MAX_AGE", 600);
+        let config = AppConfig::load().expect("valid config");
+        assert_eq!(config.cors.max_age, Some(600));
+        Ok(())
+    });
+}
@@ -0,0 +1,176 @@
+// The embedded client replaces the files these tests write
+#![cfg_attr(feature = "embed-client", allow(dead_code, unused_imports))]
+
+use rocket::http::{ContentType, Status};
+use rocket::local::blocking::{Client, LocalResponse};
+use server::{build_rocket, paths, AppConfig, ErrorBody, FrontendConfig, StorageBackend};
+use std::fs;
+use std::path::Path;
+use tempdir::TempDir;
+
+const INDEX: &str = "<!DOCTYPE html><html><body>client</body></html>";
+const SCRIPT: &str = "client-0123456789abcdef.js";
+const WASM: &str = "client-0123456789abcdef_bg.wasm";
+
+/// Write a directory laid out like Trunk's `dist` output.
+fn dist(dir: &Path) {
+    fs::write(dir.join("index.html"), INDEX).unwrap();
+    fs::write(dir.join(SCRIPT), "export default function init() {}").unwrap();
+    fs::write(dir.join(WASM), b"\0asm").unwrap();
+    fs::write(dir.join("favicon.ico"), b"icon").unwrap();
+}
+
+/// A client for a server serving the client files in `dir`.
+fn frontend_client(dir: &Path) -> Client {
+    let config = AppConfig {
+        storage: StorageBackend::Memory,
+        frontend: FrontendConfig {
+            enabled: true,
+            dir: dir.to_path_buf(),
+        },
+        ..AppConfig::default()
+    };
+    Client::tracked(build_rocket(config).expect("valid config")).expect("valid rocket")
+}
+
+fn cache_control(resp: &LocalResponse) -> Option<String> {
+    resp.headers().get_one("Cache-Control").map(String::from)
+}
+
+#[test]
+#[cfg(not(feature = "embed-client"))]
+fn test_frontend_files() {
+    let dir = TempDir::new("dist").unwrap();
+    dist(dir.path());
+    let c = frontend_client(dir.path());
+
+    // the client is served at the root and revalidated on every load
+    let resp = c.get("/").dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    assert_eq!(resp.content_type(), Some(ContentType::HTML));
+    assert_eq!(cache_control(&resp).as_deref(), Some("no-cache"));
+    assert_eq!(resp.into_string().as_deref(), Some(INDEX));
+
+    // hashed assets can be cached for good
+    for (file, content_type) in [(SCRIPT, ContentType::JavaScript), (WASM, ContentType::WASM)] {
+        let resp = c.get(format!("/{}", file)).dispatch();
+        assert_eq!(resp.status(), Status::Ok, "{}", file);
+        assert_eq!(resp.content_type(), Some(content_type));
+        assert_eq!(
+            cache_control(&resp).as_deref(),
+            Some("public, max-age=31536000, immutable")
+        );
+    }
+    let resp = c.get("/favicon.ico").dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    assert_eq!(cache_control(&resp).as_deref(), Some("no-cache"));
+
+    // client-side routes get the client
+    for path in ["/about", "/tasks-done", "/lists/3/edit"] {
+        let resp = c.get(path).dispatch();
+        assert_eq!(resp.status(), Status::Ok, "{}", path);
+        assert_eq!(cache_control(&resp).as_deref(), Some("no-cache"));
+        assert_eq!(resp.into_string().as_deref(), Some(INDEX), "{}", path);
+    }
+
+    // but missing files and API paths do not
+    for path in [
+        "/missing.js",
+        "/lists/missing.wasm",
+        "/api/v1/no/such/route",
+        "/task/not-a-number",
+        "/api",
+    ] {
+        let resp = c.get(path).dispatch();
+        assert_eq!(resp.status(), Status::NotFound, "{}", path);
+        let error: ErrorBody = resp.into_json().expect("JSON error body");
+        assert_eq!(error.code, "not_found");
+    }
+
+    // and the API still answers next to the client
+    let resp = c
+        .get(format!("{}{}", paths::API_V1, paths::TASKS))
+        .dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    assert_eq!(resp.content_type(), Some(ContentType::JSON));
+    assert_eq!(cache_control(&resp), None);
+    assert_eq!(c.get("/health").dispatch().status(), Status::Ok);
+}
+
+#[test]
+#[cfg(not(feature = "embed-client"))]
+fn test_frontend_metrics() {
+    let dir = TempDir::new("dist").unwrap();
+    dist(dir.path());
+    let c = frontend_client(dir.path());
+
+    assert_eq!(c.get("/about").dispatch().status(), Status::Ok);
+    for path in ["/missing.js", "/api/v1/no/such/route"] {
+        assert_eq!(
+            c.get(path).dispatch().status(),
+            Status::NotFound,
+            "{}",
+            path
+        );
+    }
+
+    // requests the client routes forwarded are still unmatched
+    let metrics = c.get("/metrics").dispatch().into_string().unwrap();
+    assert!(
+        metrics.contains(r#"http_requests_total{method="GET",route="/<_..>",status="200"} 1"#),
+        "{}",
+        metrics
+    );
+    let missing: Vec<&str> = metrics
+        .lines()
+        .filter(|line| line.starts_with("http_requests_total") && line.contains(r#"status="404""#))
+        .collect();
+    assert_eq!(
+        missing,
+        [r#"http_requests_total{method="GET",route="unmatched",status="404"} 2"#]
+    );
+}
+
+#[test]
+#[cfg(not(feature = "embed-client"))]
+fn test_frontend_missing() {
+    // without a built client the server runs the API alone
+    let dir = TempDir::new("dist").unwrap();
+    let c = frontend_client(&dir.path().join("dist"));
+    assert_eq!(c.get("/").dispatch().status(), Status::NotFound);
+    assert_eq!(c.get("/about").dispatch().status(), Status::NotFound);
+    assert_eq!(c.get("/health").dispatch().status(), Status::Ok);
+
+    // and serving it can be turned off
+    dist(dir.path());
+    let config = AppConfig {
+        storage: StorageBackend::Memory,
+        frontend: FrontendConfig {
+            enabled: false,
+            dir: dir.path().to_path_buf(),
+        },
+        ..AppConfig::default()
+    };
+    let c = Client::tracked(build_rocket(config).expect("valid config")).expect("valid rocket");
+    assert_eq!(c.get("/").dispatch().status(), Status::NotFound);
+}
+
+#[test]
+#[cfg(feature = "embed-client")]
+fn test_frontend_embedded() {
+    // the files come from the binary, whatever the configured directory
+    let dir = TempDir::new("dist").unwrap();
+    let c = frontend_client(&dir.path().join("dist"));
+
+    let resp = c.get("/").dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    assert_eq!(resp.content_type(), Some(ContentType::HTML));
+    assert_eq!(cache_control(&resp).as_deref(), Some("no-cache"));
+    let index = resp.into_string().expect("index.html");
+    assert_eq!(c.get("/about").dispatch().into_string(), Some(index));
+    assert_eq!(c.get("/missing.js").dispatch().status(), Status::NotFound);
+    assert_eq!(
+        c.get("/../Cargo.toml").dispatch().status(),
+        Status::NotFound
+    );
+}
@@ -1,36 +1,48 @@
-#[macro_use]
-extern crate rocket;
-
-use sled::Mode::LowSpace;
-use sled::{Config, Tree};
+use rocket::futures::future::BoxFuture;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use server::{all_routes, Task};
+use server::{
+    all_catchers, all_routes, build_rocket, paths, setup_database, AppConfig, ErrorBody,
+    FlushOnShutdown, FrontendConfig, HealthStatus, MemoryStore, MetricsConfig, OpenError,
+    SledConfig, StorageBackend, StoreError, StoreResult, StoreStats, Task, TaskPage, TaskPatch,
+    TaskQuery, TaskStore, VersionInfo,
+};
+
+/// The default configuration without the client, so that tests of
+/// `build_rocket` do not depend on whether `client/dist` has been built.
+fn test_config() -> AppConfig {
+    AppConfig {
+        frontend: FrontendConfig {
+            enabled: false,
+            ..FrontendConfig::default()
+        },
+        ..AppConfig::default()
+    }
+}
 
 /// Create an instance of Rocket suitable for tests.
-fn test_instance(db_path: PathBuf) -> rocket::Rocket<rocket::Build> {
-    let config = Config::new()
-        .path(String::from(db_path.to_str().unwrap()))
-        .mode(LowSpace)
-        .cache_capacity(1_000_000)
-        .flush_every_ms(Some(1000));
-    let tree: Tree = config.open().unwrap().open_tree("tree").unwrap();
-    let db_arc = Arc::new(tree);
-    rocket::build().mount("/", all_routes()).manage(db_arc)
+fn test_instance(store: Arc<dyn TaskStore>) -> rocket::Rocket<rocket::Build> {
+    rocket::build()
+        .mount("/", all_routes())
+        .register("/", all_catchers())
+        .manage(store)
+}
+
+/// Create an instance of Rocket backed by a sled database at `db_path`.
+fn sled_instance(db_path: PathBuf) -> rocket::Rocket<rocket::Build> {
+    let store = setup_database(db_path, &AppConfig::default().sled).expect("open sled database");
+    test_instance(Arc::new(store))
 }
 
 #[test]
 fn test_routes() {
     use rocket::http::{ContentType, Status};
     use rocket::local::blocking::Client;
-    use tempdir::TempDir;
-
-    let dir = TempDir::new("rocket").unwrap();
-    let path = dir.path().join("test_data.db");
 
     // create our test client
-    let c = Client::tracked(test_instance(path)).expect("valid rocket");
+    let c = Client::tracked(test_instance(Arc::new(MemoryStore::new()))).expect("valid rocket");
 
     // create a new task with raw json string body
     let req = c
@@ -38,7 +50,11 @@ fn test_routes() {
         .body(r#"{"id": 0, "completed": false, "description": "foo", "editing": false}"#)
         .header(ContentType::JSON);
     let resp = req.dispatch();
-    assert_eq!(resp.status(), Status::Accepted);
+    assert_eq!(resp.status(), Status::Created);
+    assert_eq!(
+        resp.headers().get_one("Location"),
+        Some(paths::task(0).as_str())
+    );
 
     let req = c.get("/task/0");
     let bod = req.dispatch().into_bytes().unwrap();
@@ -52,30 +68,1325 @@ fn test_routes() {
         description: String::from("baz"),
         completed: true,
         editing: false,
+        version: 0,
+        created_at: None,
+        updated_at: None,
+        completed_at: None,
     };
     let req = c
         .post("/task")
         .body(serde_json::to_vec(&task).unwrap())
         .header(ContentType::JSON);
     let resp = req.dispatch();
-    assert_eq!(resp.status(), Status::Accepted);
+    assert_eq!(resp.status(), Status::Created);
+    let created: Task = resp.into_json().expect("created task in the body");
+    assert_eq!(created.id, 1);
 
     // we expect our next task to have id 1
     let req = c.get("/task/1");
     let bod = req.dispatch().into_bytes().unwrap();
     let decoded: Task = serde_json::from_slice(&bod[..]).expect("not a valid task");
     assert_eq!(decoded.description, "baz");
-    assert_eq!(decoded.completed, true);
+    assert!(decoded.completed);
 
     // now fetch both tasks from /tasks
     let req = c.get("/tasks");
     let bod = req.dispatch().into_bytes().unwrap();
-    let tasks: Vec<Task> = serde_json::from_slice(&bod[..]).expect("not an array of Task");
+    let page: TaskPage = serde_json::from_slice(&bod[..]).expect("not a page of Task");
+    let tasks = page.items;
     assert_eq!(tasks.len(), 2);
 
     // Test that they come back in the order we expect, with the data we expect.
-    let foo_task = tasks.get(0).unwrap();
+    let foo_task = tasks.first().unwrap();
     let baz_task = tasks.get(1).unwrap();
     assert_eq!(foo_task.description, "foo");
     assert_eq!(baz_task.description, "baz");
 }
+
+#[test]
+fn test_build_rocket() {
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::blocking::Client;
+
+    let mut config = AppConfig {
+        storage: StorageBackend::Memory,
+        port: 9123,
+        ..test_config()
+    };
+    config.cors.allowed_origins = vec![String::from("https://example.com")];
+    let c = Client::tracked(build_rocket(config).expect("valid config")).expect("valid rocket");
+    assert_eq!(c.rocket().config().port, 9123);
+
+    let resp = c
+        .post(paths::TASK)
+        .body(r#"{"id": 0, "completed": false, "description": "foo", "editing": false}"#)
+        .header(ContentType::JSON)
+        .header(Header::new("Origin", "https://example.com"))
+        .dispatch();
+    assert_eq!(resp.status(), Status::Created);
+    assert_eq!(
+        resp.headers().get_one("Access-Control-Allow-Origin"),
+        Some("https://example.com")
+    );
+
+    // unknown routes still get JSON errors
+    let resp = c.get("/nope").dispatch();
+    assert_eq!(resp.status(), Status::NotFound);
+    let error: ErrorBody = resp.into_json().expect("JSON error body");
+    assert_eq!(error.code, "not_found");
+}
+
+#[test]
+fn test_cors() {
+    use rocket::http::{Header, Method, Status};
+    use rocket::local::blocking::Client;
+
+    let mut config = AppConfig {
+        storage: StorageBackend::Memory,
+        ..test_config()
+    };
+    config.cors.allowed_origins = vec![String::from("https://example.com")];
+    config.cors.allowed_origin_patterns = vec![String::from(r"^https://.+\.example\.com$")];
+    config.cors.allow_credentials = true;
+    config.cors.max_age = Some(600);
+    let c = Client::tracked(build_rocket(config).expect("valid config")).expect("valid rocket");
+
+    let resp = c
+        .get(paths::TASKS)
+        .header(Header::new("Origin", "https://app.example.com"))
+        .dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    let headers = resp.headers();
+    assert_eq!(
+        headers.get_one("Access-Control-Allow-Origin"),
+        Some("https://app.example.com")
+    );
+    assert_eq!(
+        headers.get_one("Access-Control-Allow-Credentials"),
+        Some("true")
+    );
+    let exposed = headers
+        .get_one("Access-Control-Expose-Headers")
+        .expect("exposed headers");
+    assert!(exposed.contains("ETag") && exposed.contains("Location"));
+
+    // preflight responses can be cached
+    let resp = c
+        .req(Method::Options, paths::TASKS)
+        .header(Header::new("Origin", "https://example.com"))
+        .header(Header::new("Access-Control-Request-Method", "POST"))
+        .dispatch();
+    assert_eq!(
+        resp.headers().get_one("Access-Control-Max-Age"),
+        Some("600")
+    );
+
+    // origins that are not on the list are refused
+    for origin in ["https://evil.com", "https://example.com.evil.com"] {
+        let resp = c
+            .get(paths::TASKS)
+            .header(Header::new("Origin", origin))
+            .dispatch();
+        assert_eq!(resp.status(), Status::Forbidden, "{}", origin);
+        assert_eq!(resp.headers().get_one("Access-Control-Allow-Origin"), None);
+    }
+}
+
+#[test]
+fn test_route_paths() {
+    // the client builds its URLs from the shared path constants
+    let routes: Vec<String> = all_routes()
+        .iter()
+        .map(|route| route.uri.path().to_string())
+        .collect();
+    for path in [
+        paths::TASK.to_string(),
+        paths::TASKS.to_string(),
+        paths::task(0).replace('0', "<id>"),
+    ] {
+        let versioned = format!("{}{}", paths::API_V1, path);
+        assert!(
+            routes.contains(&versioned),
+            "no route is mounted at {}",
+            versioned
+        );
+        // and the deprecated alias at the root
+        assert!(routes.contains(&path), "no route is mounted at {}", path);
+    }
+}
+
+#[test]
+fn test_api_versions() {
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+
+    let c = Client::tracked(test_instance(Arc::new(MemoryStore::new()))).expect("valid rocket");
+    let task = r#"{"id": 0, "completed": false, "description": "foo", "editing": false}"#;
+
+    // versioned routes point to versioned paths and are not deprecated
+    let resp = c
+        .post(format!("{}{}", paths::API_V1, paths::TASK))
+        .header(ContentType::JSON)
+        .body(task)
+        .dispatch();
+    assert_eq!(resp.status(), Status::Created);
+    let location = format!("{}{}", paths::API_V1, paths::task(0));
+    assert_eq!(resp.headers().get_one("Location"), Some(location.as_str()));
+    assert_eq!(resp.headers().get_one("Deprecation"), None);
+    let resp = c.get(&location).dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    assert_eq!(resp.headers().get_one("Deprecation"), None);
+
+    // the root aliases serve the same tasks, marked as deprecated
+    let resp = c.get(paths::task(0)).dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    assert_eq!(resp.headers().get_one("Deprecation"), Some("true"));
+    assert_eq!(
+        resp.headers().get_one("Link"),
+        Some(format!("<{}>; rel=\"successor-version\"", location).as_str())
+    );
+    let resp = c
+        .post(paths::TASK)
+        .header(ContentType::JSON)
+        .body(task)
+        .dispatch();
+    assert_eq!(
+        resp.headers().get_one("Location"),
+        Some(paths::task(1).as_str())
+    );
+    assert_eq!(resp.headers().get_one("Deprecation"), Some("true"));
+    let page: TaskPage = c
+        .get(format!("{}{}", paths::API_V1, paths::TASKS))
+        .dispatch()
+        .into_json()
+        .expect("page");
+    assert_eq!(page.items.len(), 2);
+}
+
+#[test]
+fn test_errors() {
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+
+    let c = Client::tracked(test_instance(Arc::new(MemoryStore::new()))).expect("valid rocket");
+    let task = r#"{"id": 3, "completed": false, "description": "foo", "editing": false}"#;
+
+    // reading, updating or deleting a missing task is a 404 with a JSON body
+    let resp = c.get("/task/3").dispatch();
+    assert_eq!(resp.status(), Status::NotFound);
+    let error: ErrorBody = resp.into_json().expect("JSON error body");
+    assert_eq!(error.code, "not_found");
+
+    let resp = c
+        .put("/task/3")
+        .body(task)
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::NotFound);
+
+    let resp = c.delete("/task/3").dispatch();
+    assert_eq!(resp.status(), Status::NotFound);
+    let error: ErrorBody = resp.into_json().expect("JSON error body");
+    assert_eq!(error.code, "not_found");
+
+    // the id in the body has to match the one in the path
+    let resp = c
+        .put("/task/4")
+        .body(task)
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::BadRequest);
+    let error: ErrorBody = resp.into_json().expect("JSON error body");
+    assert_eq!(error.code, "bad_request");
+
+    // malformed bodies are rejected by Rocket and still get a JSON body
+    let resp = c
+        .post("/task")
+        .body(r#"{"description": 42}"#)
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::UnprocessableEntity);
+    let error: ErrorBody = resp.into_json().expect("JSON error body");
+    assert_eq!(error.code, "unprocessable_entity");
+}
+
+#[test]
+fn test_patch() {
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("rocket").unwrap();
+    let path = dir.path().join("test_data.db");
+    let c = Client::tracked(sled_instance(path)).expect("valid rocket");
+
+    let created: Task = c
+        .post("/task")
+        .body(r#"{"id": 0, "completed": false, "description": "foo", "editing": false}"#)
+        .header(ContentType::JSON)
+        .dispatch()
+        .into_json()
+        .expect("created task");
+
+    // each patch only touches the fields it carries
+    let resp = c
+        .patch(format!("/task/{}", created.id))
+        .body(r#"{"completed": true}"#)
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    let completed: Task = resp.into_json().expect("patched task");
+    assert!(completed.completed);
+    assert_eq!(completed.description, "foo");
+
+    let resp = c
+        .patch(format!("/task/{}", created.id))
+        .body(r#"{"description": "bar", "editing": true}"#)
+        .header(ContentType::JSON)
+        .dispatch();
+    let patched: Task = resp.into_json().expect("patched task");
+    assert_eq!(
+        patched,
+        Task {
+            id: created.id,
+            completed: true,
+            description: String::from("bar"),
+            editing: true,
+            version: 3,
+            created_at: created.created_at,
+            updated_at: patched.updated_at,
+            completed_at: completed.completed_at,
+        }
+    );
+
+    let stored: Task = c
+        .get(format!("/task/{}", created.id))
+        .dispatch()
+        .into_json()
+        .expect("stored task");
+    assert_eq!(stored, patched);
+
+    // unknown fields are rejected instead of silently ignored
+    let resp = c
+        .patch(format!("/task/{}", created.id))
+        .body(r#"{"complete": false}"#)
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::UnprocessableEntity);
+
+    let resp = c
+        .patch("/task/999")
+        .body(r#"{"completed": false}"#)
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::NotFound);
+}
+
+#[test]
+fn test_if_match() {
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::blocking::Client;
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("rocket").unwrap();
+    let path = dir.path().join("test_data.db");
+    let c = Client::tracked(sled_instance(path)).expect("valid rocket");
+
+    let resp = c
+        .post("/task")
+        .body(r#"{"id": 0, "completed": false, "description": "foo", "editing": false}"#)
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.headers().get_one("ETag"), Some(r#""1""#));
+    let created: Task = resp.into_json().expect("created task");
+    assert_eq!(created.version, 1);
+    let uri = format!("/task/{}", created.id);
+
+    // both tabs load version 1
+    let resp = c.get(&uri).dispatch();
+    assert_eq!(resp.headers().get_one("ETag"), Some(r#""1""#));
+
+    // the first tab saves, moving the task to version 2
+    let resp = c
+        .patch(&uri)
+        .body(r#"{"completed": true}"#)
+        .header(ContentType::JSON)
+        .header(Header::new("If-Match", r#""1""#))
+        .dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    assert_eq!(resp.headers().get_one("ETag"), Some(r#""2""#));
+
+    // the second tab's stale writes are refused
+    let resp = c
+        .put(&uri)
+        .body(
+            serde_json::to_vec(&Task {
+                description: String::from("bar"),
+                ..created.clone()
+            })
+            .unwrap(),
+        )
+        .header(ContentType::JSON)
+        .header(Header::new("If-Match", r#""1""#))
+        .dispatch();
+    assert_eq!(resp.status(), Status::PreconditionFailed);
+    let error: ErrorBody = resp.into_json().expect("JSON error body");
+    assert_eq!(error.code, "precondition_failed");
+
+    let resp = c
+        .delete(&uri)
+        .header(Header::new("If-Match", r#""1""#))
+        .dispatch();
+    assert_eq!(resp.status(), Status::PreconditionFailed);
+
+    // the list exposes versions too
+    let page: TaskPage = c.get("/tasks").dispatch().into_json().expect("tasks");
+    let tasks = page.items;
+    assert_eq!(tasks[0].version, 2);
+    assert!(tasks[0].completed);
+
+    // malformed tags are a client error
+    for value in ["2", r#""2"#, r#""2" "3""#, ""] {
+        let resp = c
+            .delete(&uri)
+            .header(Header::new("If-Match", value))
+            .dispatch();
+        assert_eq!(resp.status(), Status::BadRequest, "{}", value);
+        let error: ErrorBody = resp.into_json().expect("JSON error body");
+        assert!(error.message.contains("If-Match"), "{}", error.message);
+    }
+
+    // weak tags never match
+    for value in [r#"W/"2""#, r#""1", W/"2""#, r#""abc""#] {
+        let resp = c
+            .delete(&uri)
+            .header(Header::new("If-Match", value))
+            .dispatch();
+        assert_eq!(resp.status(), Status::PreconditionFailed, "{}", value);
+    }
+
+    // any matching tag in a list goes through
+    let resp = c
+        .patch(&uri)
+        .body(r#"{"completed": false}"#)
+        .header(ContentType::JSON)
+        .header(Header::new("If-Match", r#""1", "2""#))
+        .dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    assert_eq!(resp.headers().get_one("ETag"), Some(r#""3""#));
+
+    // and so does the wildcard, whatever the version
+    let resp = c
+        .delete(&uri)
+        .header(Header::new("If-Match", "*"))
+        .dispatch();
+    assert_eq!(resp.status(), Status::NoContent);
+}
+
+/// Follow `next_cursor` from `uri` until the last page, returning the
+/// descriptions of every task seen and the number of pages.
+fn collect_pages(c: &rocket::local::blocking::Client, uri: &str) -> (Vec<String>, usize) {
+    let mut descriptions = Vec::new();
+    let mut pages = 0;
+    let mut next = uri.to_string();
+
+    loop {
+        let page: TaskPage = c.get(next.clone()).dispatch().into_json().expect("page");
+        pages += 1;
+        descriptions.extend(page.items.into_iter().map(|task| task.description));
+        match page.next_cursor {
+            Some(cursor) => next = format!("{}&after={}", uri, cursor),
+            None => return (descriptions, pages),
+        }
+    }
+}
+
+/// Create tasks with the given descriptions and completion states.
+fn seed(c: &rocket::local::blocking::Client, tasks: &[(&str, bool)]) {
+    use rocket::http::ContentType;
+
+    for (description, completed) in tasks {
+        let resp = c
+            .post("/task")
+            .body(format!(
+                r#"{{"id": 0, "completed": {}, "description": "{}", "editing": false}}"#,
+                completed, description
+            ))
+            .header(ContentType::JSON)
+            .dispatch();
+        assert_eq!(resp.status(), rocket::http::Status::Created);
+    }
+}
+
+/// Check filtering, sorting and pagination against a client over any store.
+fn check_list_queries(c: &rocket::local::blocking::Client) {
+    use rocket::http::Status;
+
+    seed(
+        c,
+        &[
+            ("buy milk", false),
+            ("walk dog", true),
+            ("Buy bread", false),
+            ("call mom", true),
+            ("buy eggs", true),
+        ],
+    );
+
+    // pages of two in id order
+    let (descriptions, pages) = collect_pages(c, "/tasks?limit=2");
+    assert_eq!(
+        descriptions,
+        vec!["buy milk", "walk dog", "Buy bread", "call mom", "buy eggs"]
+    );
+    assert_eq!(pages, 3);
+
+    // reverse id order
+    let (descriptions, _) = collect_pages(c, "/tasks?order=desc&limit=2");
+    assert_eq!(
+        descriptions,
+        vec!["buy eggs", "call mom", "Buy bread", "walk dog", "buy milk"]
+    );
+
+    // filters combine and the substring match ignores case
+    let (descriptions, _) = collect_pages(c, "/tasks?q=BUY&completed=false&limit=1");
+    assert_eq!(descriptions, vec!["buy milk", "Buy bread"]);
+
+    // sorting by description, in both directions
+    let (descriptions, pages) = collect_pages(c, "/tasks?sort=description&limit=2");
+    assert_eq!(
+        descriptions,
+        vec!["Buy bread", "buy eggs", "buy milk", "call mom", "walk dog"]
+    );
+    assert_eq!(pages, 3);
+    let (descriptions, _) = collect_pages(c, "/tasks?sort=description&order=desc&limit=3");
+    assert_eq!(
+        descriptions,
+        vec!["walk dog", "call mom", "buy milk", "buy eggs", "Buy bread"]
+    );
+
+    // sorting by completion keeps ids as the tie breaker
+    let (descriptions, _) = collect_pages(c, "/tasks?sort=completed&limit=2");
+    assert_eq!(
+        descriptions,
+        vec!["buy milk", "Buy bread", "walk dog", "call mom", "buy eggs"]
+    );
+
+    // the last page has no cursor
+    let page: TaskPage = c.get("/tasks").dispatch().into_json().expect("page");
+    assert_eq!(page.items.len(), 5);
+    assert_eq!(page.next_cursor, None);
+
+    // cursors are only valid for the sort field they were issued for
+    let page: TaskPage = c
+        .get("/tasks?sort=description&limit=1")
+        .dispatch()
+        .into_json()
+        .expect("page");
+    let cursor = page.next_cursor.expect("a next page");
+    let resp = c.get(format!("/tasks?after={}", cursor)).dispatch();
+    assert_eq!(resp.status(), Status::BadRequest);
+
+    // and malformed ones are rejected
+    let resp = c.get("/tasks?after=nope").dispatch();
+    assert_eq!(resp.status(), Status::BadRequest);
+
+    // unknown sort fields and orders are rejected rather than ignored
+    for path in ["/tasks?sort=bogus", "/tasks?order=sideways"] {
+        assert_eq!(c.get(path).dispatch().status(), Status::UnprocessableEntity);
+    }
+
+    // case is ignored beyond ASCII too, by every store
+    seed(c, &[("Éclair", false), ("crème brûlée", true)]);
+    let (descriptions, pages) = collect_pages(c, "/tasks?q=%C3%89&limit=1");
+    assert_eq!(descriptions, vec!["Éclair", "crème brûlée"]);
+    assert_eq!(pages, 2);
+}
+
+#[test]
+fn test_list_queries() {
+    use rocket::local::blocking::Client;
+    use tempdir::TempDir;
+
+    let c = Client::tracked(test_instance(Arc::new(MemoryStore::new()))).expect("valid rocket");
+    check_list_queries(&c);
+
+    let dir = TempDir::new("rocket").unwrap();
+    let path = dir.path().join("test_data.db");
+    let c = Client::tracked(sled_instance(path)).expect("valid rocket");
+    check_list_queries(&c);
+}
+
+/// Check that timestamps are managed by the server and follow completion.
+fn check_timestamps(c: &rocket::local::blocking::Client) {
+    use rocket::http::ContentType;
+
+    let send = |method: &str, uri: String, body: &str| -> Task {
+        let req = match method {
+            "post" => c.post(uri),
+            "put" => c.put(uri),
+            _ => c.patch(uri),
+        };
+        req.body(body)
+            .header(ContentType::JSON)
+            .dispatch()
+            .into_json()
+            .expect("task")
+    };
+
+    // timestamps sent by the client are ignored
+    let created = send(
+        "post",
+        String::from("/task"),
+        r#"{"id": 0, "completed": false, "description": "foo", "editing": false,
+            "created_at": "2000-01-01T00:00:00Z", "completed_at": "2000-01-01T00:00:00Z"}"#,
+    );
+    let created_at = created.created_at.expect("created_at is set");
+    assert_eq!(created.updated_at, Some(created_at));
+    assert_eq!(created.completed_at, None);
+    assert!(created_at.timestamp() > 946_684_800);
+
+    // and returned as RFC 3339 strings
+    let raw: serde_json::Value = c
+        .get(format!("/task/{}", created.id))
+        .dispatch()
+        .into_json()
+        .expect("raw task");
+    let raw_created_at = raw["created_at"].as_str().expect("created_at is a string");
+    assert!(chrono::DateTime::parse_from_rfc3339(raw_created_at).is_ok());
+    assert!(raw["completed_at"].is_null());
+
+    let uri = format!("/task/{}", created.id);
+    let done = send("patch", uri.clone(), r#"{"completed": true}"#);
+    let completed_at = done.completed_at.expect("completed_at is set");
+    assert_eq!(done.created_at, Some(created_at));
+    assert!(done.updated_at >= created.updated_at);
+    assert!(completed_at >= created_at);
+
+    // edits to a completed task keep its completion time
+    let edited = send(
+        "put",
+        uri.clone(),
+        &format!(
+            r#"{{"id": {}, "completed": true, "description": "bar", "editing": false,
+                "created_at": null, "completed_at": null}}"#,
+            created.id
+        ),
+    );
+    assert_eq!(edited.created_at, Some(created_at));
+    assert_eq!(edited.completed_at, Some(completed_at));
+    assert!(edited.updated_at >= done.updated_at);
+
+    // reopening clears it
+    let reopened = send("patch", uri, r#"{"completed": false}"#);
+    assert_eq!(reopened.created_at, Some(created_at));
+    assert_eq!(reopened.completed_at, None);
+}
+
+#[test]
+fn test_timestamps() {
+    use rocket::local::blocking::Client;
+    use tempdir::TempDir;
+
+    let c = Client::tracked(test_instance(Arc::new(MemoryStore::new()))).expect("valid rocket");
+    check_timestamps(&c);
+
+    let dir = TempDir::new("rocket").unwrap();
+    let path = dir.path().join("test_data.db");
+    let c = Client::tracked(sled_instance(path)).expect("valid rocket");
+    check_timestamps(&c);
+}
+
+/// Check that replacing every task gives the new tasks ids that were never used
+/// before, so that stale ETags cannot match them and creating tasks still works.
+fn check_replace_all_ids(c: &rocket::local::blocking::Client) {
+    use rocket::http::{ContentType, Header, Status};
+
+    seed(c, &[("old", false)]);
+    let page: TaskPage = c.get("/tasks").dispatch().into_json().expect("page");
+    let old = page.items.last().expect("seeded task").clone();
+
+    // more tasks than `create` tries ids before giving up
+    let tasks: Vec<Task> = (0..2000)
+        .map(|i| Task::new(format!("replaced {}", i), false))
+        .collect();
+    let resp = c
+        .post("/tasks")
+        .body(serde_json::to_vec(&tasks).unwrap())
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::NoContent);
+
+    // a tab still holding the old task cannot change a new one
+    let resp = c
+        .patch(format!("/task/{}", old.id))
+        .body(r#"{"completed": true}"#)
+        .header(ContentType::JSON)
+        .header(Header::new("If-Match", old.etag()))
+        .dispatch();
+    assert_eq!(resp.status(), Status::NotFound);
+
+    let resp = c
+        .post("/task")
+        .body(r#"{"id": 0, "completed": false, "description": "new", "editing": false}"#)
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::Created);
+    let created: Task = resp.into_json().expect("created task");
+    assert_ne!(created.id, old.id);
+}
+
+#[test]
+fn test_replace_all() {
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("rocket").unwrap();
+    let path = dir.path().join("test_data.db");
+    let c = Client::tracked(sled_instance(path)).expect("valid rocket");
+
+    // leave a gap in the ids so that index-based deletes would miss a task
+    seed(&c, &[("first", false), ("second", false), ("third", false)]);
+    let resp = c.delete("/task/1").dispatch();
+    assert_eq!(resp.status(), Status::NoContent);
+
+    let resp = c
+        .post("/tasks")
+        .body(r#"[{"id": 7, "completed": true, "description": "only", "editing": false}]"#)
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::NoContent);
+
+    let page: TaskPage = c.get("/tasks").dispatch().into_json().expect("page");
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].description, "only");
+    assert_eq!(page.items[0].version, 1);
+
+    // invalid items are all reported and nothing is written
+    let resp = c
+        .post("/tasks")
+        .body(
+            r#"[
+                {"id": 0, "completed": false, "description": "fine", "editing": false},
+                {"id": 1, "completed": false, "description": "  ", "editing": false},
+                {"id": 2, "completed": false, "description": "fine too", "editing": false},
+                {"id": 3, "completed": false, "description": "", "editing": false}
+            ]"#,
+        )
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::BadRequest);
+    let error: ErrorBody = resp.into_json().expect("JSON error body");
+    assert_eq!(error.code, "validation_failed");
+    let invalid: Vec<(Option<usize>, &str)> = error
+        .details
+        .iter()
+        .map(|detail| (detail.index, detail.field.as_str()))
+        .collect();
+    assert_eq!(
+        invalid,
+        vec![(Some(1), "description"), (Some(3), "description")]
+    );
+
+    let page: TaskPage = c.get("/tasks").dispatch().into_json().expect("page");
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].description, "only");
+
+    // single writes are validated the same way
+    let resp = c
+        .post("/task")
+        .body(r#"{"id": 0, "completed": false, "description": "", "editing": false}"#)
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::BadRequest);
+    let resp = c
+        .patch("/task/0")
+        .body(format!(r#"{{"description": "{}"}}"#, "x".repeat(1001)))
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::BadRequest);
+}
+
+#[test]
+fn test_replace_all_ids() {
+    use rocket::local::blocking::Client;
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("rocket").unwrap();
+    let c = Client::tracked(sled_instance(dir.path().join("test_data.db"))).expect("valid rocket");
+    check_replace_all_ids(&c);
+
+    let c = Client::tracked(test_instance(Arc::new(MemoryStore::new()))).expect("valid rocket");
+    check_replace_all_ids(&c);
+}
+
+#[test]
+fn test_legacy_keys_are_migrated() {
+    use rocket::local::blocking::Client;
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("rocket").unwrap();
+    let path = dir.path().join("legacy_data.db");
+
+    // write tasks under the one-byte keys used by older versions
+    {
+        let db = sled::open(&path).unwrap();
+        let tree = db.open_tree("tree").unwrap();
+        for (key, description) in [(0u8, "first"), (7u8, "second")] {
+            let value = format!(
+                r#"{{"id": {}, "completed": false, "description": "{}", "editing": false}}"#,
+                key, description
+            );
+            tree.insert([key], value.as_bytes()).unwrap();
+        }
+        db.flush().unwrap();
+    }
+
+    let c = Client::tracked(sled_instance(path)).expect("valid rocket");
+
+    let bod = c.get("/tasks").dispatch().into_bytes().unwrap();
+    let page: TaskPage = serde_json::from_slice(&bod[..]).expect("not a page of Task");
+    let tasks = page.items;
+    let ids: Vec<u64> = tasks.iter().map(|task| task.id).collect();
+    assert_eq!(ids, vec![0, 7]);
+
+    let bod = c.get("/task/7").dispatch().into_bytes().unwrap();
+    let decoded: Task = serde_json::from_slice(&bod[..]).expect("not a valid task");
+    assert_eq!(decoded.description, "second");
+
+    // new tasks must not overwrite migrated ones
+    let resp = c
+        .post("/task")
+        .body(r#"{"id": 0, "completed": false, "description": "third", "editing": false}"#)
+        .header(rocket::http::ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), rocket::http::Status::Created);
+
+    let bod = c.get("/tasks").dispatch().into_bytes().unwrap();
+    let page: TaskPage = serde_json::from_slice(&bod[..]).expect("not a page of Task");
+    let tasks = page.items;
+    assert_eq!(tasks.len(), 3);
+    assert_eq!(tasks[0].description, "first");
+}
+
+/// A memory store that counts how often it is flushed and can be made unhealthy.
+#[derive(Default)]
+struct InstrumentedStore {
+    store: MemoryStore,
+    flushes: Arc<AtomicUsize>,
+    broken: AtomicBool,
+}
+
+impl TaskStore for InstrumentedStore {
+    fn create(&self, task: Task) -> StoreResult<Task> {
+        self.store.create(task)
+    }
+
+    fn get(&self, id: u64) -> StoreResult<Task> {
+        self.store.get(id)
+    }
+
+    fn list(&self, query: &TaskQuery) -> StoreResult<TaskPage> {
+        self.store.list(query)
+    }
+
+    fn update(&self, id: u64, task: Task, expected: Option<&[u64]>) -> StoreResult<Task> {
+        self.store.update(id, task, expected)
+    }
+
+    fn patch(&self, id: u64, patch: &TaskPatch, expected: Option<&[u64]>) -> StoreResult<Task> {
+        self.store.patch(id, patch, expected)
+    }
+
+    fn delete(&self, id: u64, expected: Option<&[u64]>) -> StoreResult<()> {
+        self.store.delete(id, expected)
+    }
+
+    fn replace_all(&self, tasks: Vec<Task>) -> StoreResult<()> {
+        self.store.replace_all(tasks)
+    }
+
+    fn backend(&self) -> StorageBackend {
+        self.store.backend()
+    }
+
+    fn check(&self) -> StoreResult<()> {
+        if self.broken.load(Ordering::SeqCst) {
+            return Err(StoreError::Backend(String::from("disk on fire")));
+        }
+        self.store.check()
+    }
+
+    fn stats(&self) -> StoreResult<StoreStats> {
+        self.store.stats()
+    }
+
+    fn flush(&self) -> BoxFuture<'static, StoreResult<usize>> {
+        self.flushes.fetch_add(1, Ordering::SeqCst);
+        self.store.flush()
+    }
+}
+
+#[test]
+fn test_health() {
+    use rocket::http::Status;
+    use rocket::local::blocking::Client;
+    use server::health_routes;
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("rocket").unwrap();
+    let config = AppConfig {
+        database_path: dir.path().join("test_data.db"),
+        ..test_config()
+    };
+    let c = Client::tracked(build_rocket(config).expect("valid config")).expect("valid rocket");
+
+    let health: HealthStatus = c.get("/health").dispatch().into_json().expect("health");
+    assert_eq!(health.status, "ok");
+    let ready: HealthStatus = c.get("/ready").dispatch().into_json().expect("ready");
+    assert_eq!(ready.status, "ready");
+    let version: VersionInfo = c.get("/version").dispatch().into_json().expect("version");
+    assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(version.storage, StorageBackend::Sled);
+
+    // the probe key stays out of the task list
+    let page: TaskPage = c.get(paths::TASKS).dispatch().into_json().expect("page");
+    assert!(page.items.is_empty());
+
+    // a store that cannot be used makes the server unready but still alive
+    let store = Arc::new(InstrumentedStore::default());
+    let rocket = test_instance(store.clone()).mount("/", health_routes());
+    let c = Client::tracked(rocket).expect("valid rocket");
+    store.broken.store(true, Ordering::SeqCst);
+    assert_eq!(c.get("/health").dispatch().status(), Status::Ok);
+    let resp = c.get("/ready").dispatch();
+    assert_eq!(resp.status(), Status::ServiceUnavailable);
+    let error: ErrorBody = resp.into_json().expect("JSON error body");
+    assert_eq!(error.code, "not_ready");
+    let version: VersionInfo = c.get("/version").dispatch().into_json().expect("version");
+    assert_eq!(version.storage, StorageBackend::Memory);
+}
+
+#[test]
+fn test_openapi() {
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use server::{DOCS_PATH, OPENAPI_PATH};
+    use std::collections::BTreeSet;
+
+    let config = AppConfig {
+        storage: StorageBackend::Memory,
+        ..test_config()
+    };
+    let c = Client::tracked(build_rocket(config).expect("valid config")).expect("valid rocket");
+
+    let spec: serde_json::Value = c
+        .get(OPENAPI_PATH)
+        .dispatch()
+        .into_json()
+        .expect("OpenAPI document");
+    assert!(spec["openapi"]
+        .as_str()
+        .is_some_and(|v| v.starts_with("3.")));
+
+    // every versioned route is documented, and nothing else
+    let documented: BTreeSet<(String, String)> = spec["paths"]
+        .as_object()
+        .expect("paths")
+        .iter()
+        .flat_map(|(path, item)| {
+            item.as_object()
+                .expect("path item")
+                .keys()
+                .filter(|key| ["get", "post", "put", "patch", "delete"].contains(&key.as_str()))
+                .map(move |method| (method.to_uppercase(), path.clone()))
+        })
+        .collect();
+    let routed: BTreeSet<(String, String)> = all_routes()
+        .iter()
+        .filter(|route| route.uri.path().starts_with(paths::API_V1))
+        .map(|route| {
+            let path = route.uri.path().replace('<', "{").replace('>', "}");
+            (route.method.as_str().to_string(), path)
+        })
+        .collect();
+    assert_eq!(documented, routed);
+
+    // parameters come from the route signatures
+    let get_task = &spec["paths"]["/api/v1/task/{id}"]["get"];
+    assert_eq!(get_task["parameters"][0]["name"], "id");
+    assert_eq!(get_task["parameters"][0]["in"], "path");
+    let patch_task = &spec["paths"]["/api/v1/task/{id}"]["patch"];
+    assert!(patch_task["parameters"]
+        .as_array()
+        .expect("parameters")
+        .iter()
+        .any(|p| p["name"] == "If-Match" && p["in"] == "header"));
+    let list_params: BTreeSet<&str> = spec["paths"]["/api/v1/tasks"]["get"]["parameters"]
+        .as_array()
+        .expect("parameters")
+        .iter()
+        .filter_map(|p| p["name"].as_str())
+        .collect();
+    assert_eq!(
+        list_params,
+        BTreeSet::from(["completed", "q", "sort", "order", "limit", "after"])
+    );
+    let sort = &spec["paths"]["/api/v1/tasks"]["get"]["parameters"][2];
+    assert_eq!(sort["name"], "sort");
+    assert_eq!(sort["required"], false);
+    assert!(spec["components"]["schemas"]["Task"].is_object());
+
+    let resp = c.get(DOCS_PATH).dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    assert_eq!(resp.content_type(), Some(ContentType::HTML));
+    assert!(resp.into_string().expect("docs").contains(OPENAPI_PATH));
+}
+
+#[test]
+fn test_request_ids() {
+    use rocket::http::{Header, Status};
+    use rocket::local::blocking::Client;
+    use server::REQUEST_ID_HEADER;
+
+    let config = AppConfig {
+        storage: StorageBackend::Memory,
+        ..test_config()
+    };
+    let c = Client::tracked(build_rocket(config).expect("valid config")).expect("valid rocket");
+
+    // generated when missing, and different for every request
+    let first = c.get(paths::TASKS).dispatch();
+    let second = c.get("/api/v1/no/such/path").dispatch();
+    assert_eq!(second.status(), Status::NotFound);
+    let first = first
+        .headers()
+        .get_one(REQUEST_ID_HEADER)
+        .expect("request id");
+    let second = second
+        .headers()
+        .get_one(REQUEST_ID_HEADER)
+        .expect("request id");
+    assert_eq!(first.len(), 36);
+    assert_ne!(first, second);
+
+    // an incoming id is echoed back
+    let resp = c
+        .get(paths::TASKS)
+        .header(Header::new(REQUEST_ID_HEADER, "abc-123"))
+        .dispatch();
+    assert_eq!(resp.headers().get_one(REQUEST_ID_HEADER), Some("abc-123"));
+
+    // unless it is not fit to be logged
+    for bad in ["", "has spaces", &"x".repeat(129)] {
+        let resp = c
+            .get(paths::TASKS)
+            .header(Header::new(REQUEST_ID_HEADER, bad.to_string()))
+            .dispatch();
+        let id = resp
+            .headers()
+            .get_one(REQUEST_ID_HEADER)
+            .expect("request id");
+        assert_ne!(id, bad);
+        assert_eq!(id.len(), 36);
+    }
+}
+
+#[test]
+fn test_metrics() {
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::blocking::Client;
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("rocket").unwrap();
+    let config = AppConfig {
+        database_path: dir.path().join("test_data.db"),
+        metrics: MetricsConfig {
+            enabled: true,
+            token: Some(String::from("s3cret")),
+        },
+        ..test_config()
+    };
+    let c = Client::tracked(build_rocket(config).expect("valid config")).expect("valid rocket");
+
+    let mut ids = Vec::new();
+    for description in ["foo", "bar"] {
+        let task: Task = c
+            .post(paths::TASK)
+            .header(ContentType::JSON)
+            .body(format!(
+                r#"{{"id": 0, "description": "{}", "completed": false, "editing": false}}"#,
+                description
+            ))
+            .dispatch()
+            .into_json()
+            .expect("created task");
+        ids.push(task.id);
+    }
+    let resp = c
+        .patch(paths::task(ids[0]))
+        .header(ContentType::JSON)
+        .body(r#"{"completed": true}"#)
+        .dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    assert_eq!(c.get(paths::task(ids[1])).dispatch().status(), Status::Ok);
+    assert_eq!(
+        c.get("/api/v1/no/such/path").dispatch().status(),
+        Status::NotFound
+    );
+
+    // the token is required
+    let resp = c.get("/metrics").dispatch();
+    assert_eq!(resp.status(), Status::Unauthorized);
+    let error: ErrorBody = resp.into_json().expect("JSON error body");
+    assert_eq!(error.code, "unauthorized");
+    let resp = c
+        .get("/metrics")
+        .header(Header::new("Authorization", "Bearer guess"))
+        .dispatch();
+    assert_eq!(resp.status(), Status::Unauthorized);
+
+    let resp = c
+        .get("/metrics")
+        .header(Header::new("Authorization", "Bearer s3cret"))
+        .dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+    let content_type = resp.content_type().expect("content type");
+    assert!(content_type.is_plain());
+    assert!(content_type
+        .params()
+        .any(|(k, v)| k == "version" && v == "0.0.4"));
+    let body = resp.into_string().expect("metrics");
+    for line in [
+        r#"http_requests_total{method="POST",route="/task",status="201"} 2"#,
+        r#"http_requests_total{method="PATCH",route="/task/<id>",status="200"} 1"#,
+        r#"http_requests_total{method="GET",route="/task/<id>",status="200"} 1"#,
+        r#"http_requests_total{method="GET",route="unmatched",status="404"} 1"#,
+        r#"http_requests_total{method="GET",route="/metrics",status="401"} 2"#,
+        r#"http_request_duration_seconds_count{method="POST",route="/task"} 2"#,
+        "tasks 2",
+        "tasks_completed 1",
+    ] {
+        assert!(
+            body.lines().any(|l| l == line),
+            "{} missing from\n{}",
+            line,
+            body
+        );
+    }
+    // sled only grows its files once it flushes, so just check the size is there
+    let size = body
+        .lines()
+        .find_map(|l| l.strip_prefix("store_size_on_disk_bytes "))
+        .expect("size on disk");
+    assert!(size.parse::<u64>().is_ok(), "{}", size);
+
+    // disabled metrics are not served
+    let config = AppConfig {
+        storage: StorageBackend::Memory,
+        metrics: MetricsConfig {
+            enabled: false,
+            token: None,
+        },
+        ..test_config()
+    };
+    let c = Client::tracked(build_rocket(config).expect("valid config")).expect("valid rocket");
+    assert_eq!(c.get("/metrics").dispatch().status(), Status::NotFound);
+}
+
+#[test]
+fn test_flush_on_shutdown() {
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+
+    let store = InstrumentedStore::default();
+    let flushes = store.flushes.clone();
+    let rocket = test_instance(Arc::new(store)).attach(FlushOnShutdown::default());
+    let c = Client::tracked(rocket).expect("valid rocket");
+
+    let resp = c
+        .post(paths::TASK)
+        .body(r#"{"id": 0, "completed": false, "description": "foo", "editing": false}"#)
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::Created);
+    drop(resp);
+    assert_eq!(flushes.load(Ordering::SeqCst), 0);
+
+    c.terminate();
+    assert_eq!(flushes.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_durable_writes() {
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("rocket").unwrap();
+    let sled = SledConfig {
+        flush_every_ms: 0,
+        ..SledConfig::default()
+    };
+    let buffered = setup_database(dir.path().join("buffered.db"), &sled).expect("open sled");
+    let durable = SledConfig {
+        durable: true,
+        ..sled.clone()
+    };
+    let durable = setup_database(dir.path().join("durable.db"), &durable).expect("open sled");
+
+    for store in [&buffered, &durable] {
+        store.create(Task::new("foo", false)).expect("created task");
+    }
+
+    // only the buffered store has anything left to write
+    assert!(rocket::execute(buffered.flush()).expect("flushed") > 0);
+    assert_eq!(rocket::execute(durable.flush()).expect("flushed"), 0);
+}
+
+#[test]
+fn test_open_errors() {
+    use rocket::error::ErrorKind;
+    use rocket::local::blocking::Client;
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("rocket").unwrap();
+    let sled = AppConfig::default().sled;
+
+    // a database held open elsewhere is reported as locked
+    let path = dir.path().join("locked.db");
+    let _held = setup_database(path.clone(), &sled).expect("open sled database");
+    let err = setup_database(path.clone(), &sled)
+        .err()
+        .expect("lock error");
+    assert!(matches!(err, OpenError::Locked(_)), "{:?}", err);
+    assert!(err.to_string().contains("another process"));
+
+    // and stops the server from igniting instead of exiting the process
+    let config = AppConfig {
+        database_path: path,
+        ..test_config()
+    };
+    let err =
+        Client::tracked(build_rocket(config).expect("valid config")).expect_err("ignite error");
+    assert!(matches!(err.kind(), ErrorKind::FailedFairings(_)));
+
+    // damaged files are reported as corrupted
+    let path = dir.path().join("corrupted.db");
+    std::fs::create_dir(&path).unwrap();
+    std::fs::write(path.join("conf"), b"definitely not a sled config").unwrap();
+    let err = setup_database(path, &sled).err().expect("corruption error");
+    assert!(matches!(err, OpenError::Corrupted { .. }), "{:?}", err);
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_routes() {
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use server::setup_sqlite_database;
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("rocket").unwrap();
+    let path = dir.path().join("test_data.sqlite");
+
+    // files that are not SQLite databases are reported as corrupted
+    let garbage = dir.path().join("garbage.sqlite");
+    std::fs::write(&garbage, [0xde; 4096]).unwrap();
+    let err = setup_sqlite_database(garbage)
+        .err()
+        .expect("corruption error");
+    assert!(matches!(err, OpenError::Corrupted { .. }), "{:?}", err);
+
+    let store = Arc::new(setup_sqlite_database(path).expect("open sqlite database"));
+    let c = Client::tracked(test_instance(store)).expect("valid rocket");
+
+    let list_path = dir.path().join("test_list.sqlite");
+    let list_store = Arc::new(setup_sqlite_database(list_path).expect("open sqlite database"));
+    let list = Client::tracked(test_instance(list_store.clone())).expect("valid rocket");
+    check_list_queries(&list);
+    check_timestamps(&list);
+    let stats = list_store.stats().expect("stats");
+    assert!(stats.tasks > 0 && stats.completed > 0 && stats.completed <= stats.tasks);
+    assert!(stats.size_on_disk.is_some_and(|size| size > 0));
+
+    let replace_path = dir.path().join("test_replace.sqlite");
+    let replace_store =
+        Arc::new(setup_sqlite_database(replace_path).expect("open sqlite database"));
+    check_replace_all_ids(&Client::tracked(test_instance(replace_store)).expect("valid rocket"));
+
+    for description in ["foo", "baz"] {
+        let resp = c
+            .post("/task")
+            .body(format!(
+                r#"{{"id": 0, "completed": false, "description": "{}", "editing": false}}"#,
+                description
+            ))
+            .header(ContentType::JSON)
+            .dispatch();
+        assert_eq!(resp.status(), Status::Created);
+    }
+
+    let bod = c.get("/tasks").dispatch().into_bytes().unwrap();
+    let page: TaskPage = serde_json::from_slice(&bod[..]).expect("not a page of Task");
+    let tasks = page.items;
+    let descriptions: Vec<&str> = tasks.iter().map(|t| t.description.as_str()).collect();
+    assert_eq!(descriptions, vec!["foo", "baz"]);
+
+    // update the second task and read it back
+    let id = tasks[1].id;
+    let updated = Task {
+        id,
+        completed: true,
+        description: String::from("baz"),
+        editing: false,
+        version: 0,
+        created_at: None,
+        updated_at: None,
+        completed_at: None,
+    };
+    let resp = c
+        .put(format!("/task/{}", id))
+        .body(serde_json::to_vec(&updated).unwrap())
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::Ok);
+
+    let bod = c
+        .get(format!("/task/{}", id))
+        .dispatch()
+        .into_bytes()
+        .unwrap();
+    let decoded: Task = serde_json::from_slice(&bod[..]).expect("not a valid task");
+    assert_eq!(
+        decoded,
+        Task {
+            version: 2,
+            created_at: decoded.created_at,
+            updated_at: decoded.updated_at,
+            completed_at: decoded.completed_at,
+            ..updated
+        }
+    );
+
+    // delete the first task
+    let resp = c.delete(format!("/task/{}", tasks[0].id)).dispatch();
+    assert_eq!(resp.status(), Status::NoContent);
+    assert_eq!(
+        c.get(format!("/task/{}", tasks[0].id)).dispatch().status(),
+        Status::NotFound
+    );
+
+    // replace everything
+    let resp = c
+        .post("/tasks")
+        .body(r#"[{"id": 9, "completed": false, "description": "qux", "editing": false}]"#)
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(resp.status(), Status::NoContent);
+
+    let bod = c.get("/tasks").dispatch().into_bytes().unwrap();
+    let page: TaskPage = serde_json::from_slice(&bod[..]).expect("not a page of Task");
+    let tasks = page.items;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].description, "qux");
+}